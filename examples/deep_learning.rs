@@ -3,8 +3,8 @@ use std::time::SystemTime;
 use carnaval_rust::{
     activation::ActivationFunctionType,
     layer::{
-        conv2d::Conv2dLayer, dense::DenseLayer, flatten::FlattenLayer, maxpool2d::MaxPool2dLayer,
-        Layer,
+        conv2d::Conv2dLayer, dense::DenseLayer, globalpool2d::GlobalAveragePooling2dLayer,
+        maxpool2d::MaxPool2dLayer, Layer,
     },
     model::sequential::SequentialModel,
 };
@@ -52,27 +52,34 @@ fn create_deep_learning_model(input_shape: &[usize]) -> SequentialModel {
 
     let layer5 = MaxPool2dLayer::new((2, 2), None, None);
 
-    let shape = layer5.output_dim(layer4.output_dim);
-    let dense_0_input_size = shape.0 * shape.1 * shape.2;
-
-    let layer6 = FlattenLayer::new();
-    let layer7 = DenseLayer::new(dense_0_input_size, 128, Some(ActivationFunctionType::Relu));
+    // Averaging every channel keeps the classifier small, a flattened
+    // 26x26x64 map would need millions of dense weights
+    let layer6 = GlobalAveragePooling2dLayer::new();
+    let shape = layer6.output_dim(layer5.output_dim(layer4.output_dim));
+    let layer7 = DenseLayer::new(shape.1, 128, Some(ActivationFunctionType::Relu));
     let layer8 = DenseLayer::new(128, 2, Some(ActivationFunctionType::Sigmoid));
 
-    let mut nn = SequentialModel::new(9);
+    let mut nn =
+        SequentialModel::with_input_dim((input_shape[0], input_shape[1], input_shape[2]), 9);
 
-    nn.push_layer("Conv2D Layer 0".to_string(), Layer::Conv2d(layer0));
-    nn.push_layer("MaxPool2D Layer 0".to_string(), Layer::MaxPool2d(layer1));
-    nn.push_layer("Conv2D Layer 1".to_string(), Layer::Conv2d(layer2));
-    nn.push_layer("MaxPool2D Layer 1".to_string(), Layer::MaxPool2d(layer3));
-    nn.push_layer("Conv2D Layer 2".to_string(), Layer::Conv2d(layer4));
-    nn.push_layer("MaxPool2D Layer 2".to_string(), Layer::MaxPool2d(layer5));
-    nn.push_layer("Flatten Layer".to_string(), Layer::Flatten(layer6));
-    nn.push_layer("Dense layer 0".to_string(), Layer::Dense(layer7));
-    nn.push_layer(
-        "Dense layer 1 (classification)".to_string(),
-        Layer::Dense(layer8),
-    );
+    let named_layers = [
+        ("Conv2D Layer 0", Layer::Conv2d(layer0)),
+        ("MaxPool2D Layer 0", Layer::MaxPool2d(layer1)),
+        ("Conv2D Layer 1", Layer::Conv2d(layer2)),
+        ("MaxPool2D Layer 1", Layer::MaxPool2d(layer3)),
+        ("Conv2D Layer 2", Layer::Conv2d(layer4)),
+        ("MaxPool2D Layer 2", Layer::MaxPool2d(layer5)),
+        (
+            "Global Average Pooling Layer",
+            Layer::GlobalAveragePooling2d(layer6),
+        ),
+        ("Dense layer 0", Layer::Dense(layer7)),
+        ("Dense layer 1 (classification)", Layer::Dense(layer8)),
+    ];
+    for (layer_name, layer) in named_layers {
+        nn.push_layer(layer_name.to_string(), layer)
+            .expect("Layer shapes should line up here");
+    }
 
     nn
 }
@@ -80,6 +87,7 @@ fn create_deep_learning_model(input_shape: &[usize]) -> SequentialModel {
 fn main() {
     let input = Array::random((224, 224, 3), Uniform::new(0., 1.));
     let model = create_deep_learning_model(input.shape());
+    println!("{}", model.summary());
     let start_time = SystemTime::now();
     let result = model.forward(&input);
     let duration = SystemTime::now()
@@ -33,6 +33,78 @@ pub fn tanh(x: &f32) -> f32 {
 }
 
 pub fn softmax(x: &Array<f32, Ix3>) -> Array<f32, Ix3> {
-    let exp_scores = x.clone().map(|x| x.exp());
+    // shifting by the max does not change the result but avoids overflowing
+    let max_score = x.fold(f32::NEG_INFINITY, |max, x| max.max(*x));
+    let exp_scores = x.map(|x| (x - max_score).exp());
     exp_scores.clone() / exp_scores.sum()
 }
+
+// derivatives, evaluated at the pre-activation value
+
+pub fn sigmoid_derivative(x: &f32) -> f32 {
+    let activation = sigmoid(x);
+    activation * (1.0 - activation)
+}
+
+pub fn relu_derivative(x: &f32) -> f32 {
+    if *x > 0.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+pub fn leaky_relu_derivative(x: &f32, alpha: Option<f32>) -> f32 {
+    if *x > 0.0 {
+        1.0
+    } else {
+        alpha.unwrap_or(0.1)
+    }
+}
+
+pub fn tanh_derivative(x: &f32) -> f32 {
+    1.0 - x.tanh().powi(2)
+}
+
+// Softmax couples every element, so instead of an element-wise derivative its
+// Jacobian is applied to the upstream gradient: s * (g - sum(g * s))
+pub fn softmax_backward(
+    output: &Array<f32, Ix3>,
+    grad_output: &Array<f32, Ix3>,
+) -> Array<f32, Ix3> {
+    let weighted_sum = (output * grad_output).sum();
+    output * &(grad_output - weighted_sum)
+}
+
+// Applies the activation to every value, softmax to the whole array
+pub fn activate(
+    activation_function: ActivationFunctionType,
+    preactivation: &Array<f32, Ix3>,
+) -> Array<f32, Ix3> {
+    match activation_function {
+        ActivationFunctionType::Relu => preactivation.map(relu),
+        ActivationFunctionType::Sigmoid => preactivation.map(sigmoid),
+        ActivationFunctionType::LeakyRelu => preactivation.map(|x| leaky_relu(x, None)),
+        ActivationFunctionType::Tanh => preactivation.map(tanh),
+        ActivationFunctionType::Softmax => softmax(preactivation),
+        ActivationFunctionType::None => preactivation.clone(),
+    }
+}
+
+pub fn activation_backward(
+    activation_function: ActivationFunctionType,
+    preactivation: &Array<f32, Ix3>,
+    output: &Array<f32, Ix3>,
+    grad_output: &Array<f32, Ix3>,
+) -> Array<f32, Ix3> {
+    match activation_function {
+        ActivationFunctionType::Relu => preactivation.map(relu_derivative) * grad_output,
+        ActivationFunctionType::Sigmoid => preactivation.map(sigmoid_derivative) * grad_output,
+        ActivationFunctionType::LeakyRelu => {
+            preactivation.map(|x| leaky_relu_derivative(x, None)) * grad_output
+        }
+        ActivationFunctionType::Tanh => preactivation.map(tanh_derivative) * grad_output,
+        ActivationFunctionType::Softmax => softmax_backward(output, grad_output),
+        ActivationFunctionType::None => grad_output.clone(),
+    }
+}
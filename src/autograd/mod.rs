@@ -0,0 +1,554 @@
+// reverse-mode automatic differentiation
+//
+// A Tape records every operation applied to its variables. Calling backward
+// walks the records from the output back to the leaves and returns the
+// gradient of every variable, so a layer written with tape operations does
+// not need hand-derived gradients.
+
+use std::error::Error;
+
+use ndarray::{Array, ArrayD, Axis, Ix2, Ix3, IxDyn, Slice, Zip};
+
+// Handle to a value recorded on a Tape
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Var(usize);
+
+enum Operation {
+    Leaf,
+    Add(Var, Var),
+    Sub(Var, Var),
+    Mul(Var, Var),
+    Div(Var, Var),
+    Scale(Var, f32),
+    AddScalar(Var),
+    MatMul(Var, Var),
+    Transpose(Var),
+    Exp(Var),
+    Ln(Var),
+    Sqrt(Var),
+    Powi(Var, i32),
+    Relu(Var),
+    LeakyRelu(Var, f32),
+    Sigmoid(Var),
+    Tanh(Var),
+    Sum(Var),
+    SumAxis(Var, usize),
+    MeanAxis(Var, usize),
+    Softmax(Var, usize),
+    Slice(Var, Vec<(usize, usize)>),
+    Pad(Var, Vec<(usize, usize)>),
+    Reshape(Var),
+    Concat(Vec<Var>, usize),
+}
+
+struct Node {
+    value: ArrayD<f32>,
+    operation: Operation,
+}
+
+#[derive(Default)]
+pub struct Tape {
+    nodes: Vec<Node>,
+}
+
+// Gradients produced by Tape::backward, indexed by Var
+pub struct Gradients {
+    gradients: Vec<Option<ArrayD<f32>>>,
+}
+
+impl Gradients {
+    // None when the variable does not contribute to the output
+    pub fn get(&self, var: Var) -> Option<&ArrayD<f32>> {
+        self.gradients.get(var.0).and_then(Option::as_ref)
+    }
+}
+
+impl Tape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn variable(&mut self, value: ArrayD<f32>) -> Var {
+        self.push(value, Operation::Leaf)
+    }
+
+    pub fn value(&self, var: Var) -> &ArrayD<f32> {
+        &self.nodes[var.0].value
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn push(&mut self, value: ArrayD<f32>, operation: Operation) -> Var {
+        self.nodes.push(Node { value, operation });
+        Var(self.nodes.len() - 1)
+    }
+}
+
+// Element-wise operations broadcast their operands like numpy does
+impl Tape {
+    pub fn add(&mut self, a: Var, b: Var) -> Result<Var, Box<dyn Error>> {
+        self.check_broadcast(a, b)?;
+        let value = self.value(a) + self.value(b);
+        Ok(self.push(value, Operation::Add(a, b)))
+    }
+
+    pub fn sub(&mut self, a: Var, b: Var) -> Result<Var, Box<dyn Error>> {
+        self.check_broadcast(a, b)?;
+        let value = self.value(a) - self.value(b);
+        Ok(self.push(value, Operation::Sub(a, b)))
+    }
+
+    pub fn mul(&mut self, a: Var, b: Var) -> Result<Var, Box<dyn Error>> {
+        self.check_broadcast(a, b)?;
+        let value = self.value(a) * self.value(b);
+        Ok(self.push(value, Operation::Mul(a, b)))
+    }
+
+    pub fn div(&mut self, a: Var, b: Var) -> Result<Var, Box<dyn Error>> {
+        self.check_broadcast(a, b)?;
+        let value = self.value(a) / self.value(b);
+        Ok(self.push(value, Operation::Div(a, b)))
+    }
+
+    pub fn scale(&mut self, a: Var, factor: f32) -> Var {
+        let value = self.value(a) * factor;
+        self.push(value, Operation::Scale(a, factor))
+    }
+
+    pub fn add_scalar(&mut self, a: Var, scalar: f32) -> Var {
+        let value = self.value(a) + scalar;
+        self.push(value, Operation::AddScalar(a))
+    }
+
+    pub fn exp(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(f32::exp);
+        self.push(value, Operation::Exp(a))
+    }
+
+    pub fn ln(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(f32::ln);
+        self.push(value, Operation::Ln(a))
+    }
+
+    pub fn sqrt(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(f32::sqrt);
+        self.push(value, Operation::Sqrt(a))
+    }
+
+    pub fn powi(&mut self, a: Var, exponent: i32) -> Var {
+        let value = self.value(a).mapv(|x| x.powi(exponent));
+        self.push(value, Operation::Powi(a, exponent))
+    }
+
+    pub fn relu(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(|x| x.max(0.0));
+        self.push(value, Operation::Relu(a))
+    }
+
+    pub fn leaky_relu(&mut self, a: Var, alpha: f32) -> Var {
+        let value = self.value(a).mapv(|x| if x > 0.0 { x } else { alpha * x });
+        self.push(value, Operation::LeakyRelu(a, alpha))
+    }
+
+    pub fn sigmoid(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(|x| 1.0 / (1.0 + (-x).exp()));
+        self.push(value, Operation::Sigmoid(a))
+    }
+
+    pub fn tanh(&mut self, a: Var) -> Var {
+        let value = self.value(a).mapv(f32::tanh);
+        self.push(value, Operation::Tanh(a))
+    }
+
+    fn check_broadcast(&self, a: Var, b: Var) -> Result<(), Box<dyn Error>> {
+        let a_shape = self.value(a).shape();
+        let b_shape = self.value(b).shape();
+        let compatible = a_shape
+            .iter()
+            .rev()
+            .zip(b_shape.iter().rev())
+            .all(|(a_len, b_len)| a_len == b_len || *a_len == 1 || *b_len == 1);
+        if compatible {
+            Ok(())
+        } else {
+            Err(Box::new(TapeError::ShapeMismatchError))
+        }
+    }
+}
+
+// Matrix, reduction and shape operations
+impl Tape {
+    // Product of two 2-D variables
+    pub fn matmul(&mut self, a: Var, b: Var) -> Result<Var, Box<dyn Error>> {
+        let a_matrix = self.value(a).view().into_dimensionality::<Ix2>()?;
+        let b_matrix = self.value(b).view().into_dimensionality::<Ix2>()?;
+        if a_matrix.ncols() != b_matrix.nrows() {
+            return Err(Box::new(TapeError::ShapeMismatchError));
+        }
+        let value = a_matrix.dot(&b_matrix).into_dyn();
+        Ok(self.push(value, Operation::MatMul(a, b)))
+    }
+
+    // Reverses the order of the axes
+    pub fn transpose(&mut self, a: Var) -> Var {
+        let value = self.value(a).clone().reversed_axes();
+        self.push(value, Operation::Transpose(a))
+    }
+
+    // Sum of every element, as a 0-D variable
+    pub fn sum(&mut self, a: Var) -> Var {
+        let value = Array::from_elem(IxDyn(&[]), self.value(a).sum());
+        self.push(value, Operation::Sum(a))
+    }
+
+    // Sum along axis, keeping it with length 1 so the result broadcasts back
+    pub fn sum_axis(&mut self, a: Var, axis: usize) -> Result<Var, Box<dyn Error>> {
+        self.check_axis(a, axis)?;
+        let value = self.value(a).sum_axis(Axis(axis)).insert_axis(Axis(axis));
+        Ok(self.push(value, Operation::SumAxis(a, axis)))
+    }
+
+    // Mean along axis, keeping it with length 1 so the result broadcasts back
+    pub fn mean_axis(&mut self, a: Var, axis: usize) -> Result<Var, Box<dyn Error>> {
+        self.check_axis(a, axis)?;
+        let value = self
+            .value(a)
+            .mean_axis(Axis(axis))
+            .ok_or(TapeError::EmptyAxisError)?
+            .insert_axis(Axis(axis));
+        Ok(self.push(value, Operation::MeanAxis(a, axis)))
+    }
+
+    pub fn softmax(&mut self, a: Var, axis: usize) -> Result<Var, Box<dyn Error>> {
+        self.check_axis(a, axis)?;
+        let mut value = self.value(a).clone();
+        for mut lane in value.lanes_mut(Axis(axis)) {
+            let max = lane.fold(f32::NEG_INFINITY, |max, x| max.max(*x));
+            lane.mapv_inplace(|x| (x - max).exp());
+            let sum = lane.sum();
+            lane /= sum;
+        }
+        Ok(self.push(value, Operation::Softmax(a, axis)))
+    }
+
+    // Keeps the range [start, end) of every axis
+    pub fn slice(&mut self, a: Var, ranges: &[(usize, usize)]) -> Result<Var, Box<dyn Error>> {
+        let shape = self.value(a).shape();
+        let valid = ranges.len() == shape.len()
+            && ranges
+                .iter()
+                .zip(shape)
+                .all(|((start, end), len)| start <= end && end <= len);
+        if !valid {
+            return Err(Box::new(TapeError::InvalidRangeError));
+        }
+        let value = self
+            .value(a)
+            .slice_each_axis(|axis| {
+                let (start, end) = ranges[axis.axis.index()];
+                Slice::from(start..end)
+            })
+            .to_owned();
+        Ok(self.push(value, Operation::Slice(a, ranges.to_vec())))
+    }
+
+    // Adds (before, after) zeros around every axis
+    pub fn pad(&mut self, a: Var, padding: &[(usize, usize)]) -> Result<Var, Box<dyn Error>> {
+        let input = self.value(a);
+        if padding.len() != input.ndim() {
+            return Err(Box::new(TapeError::InvalidRangeError));
+        }
+        let padded_shape: Vec<usize> = input
+            .shape()
+            .iter()
+            .zip(padding)
+            .map(|(len, (before, after))| before + len + after)
+            .collect();
+        let mut value = Array::zeros(IxDyn(&padded_shape));
+        value
+            .slice_each_axis_mut(|axis| {
+                let (before, _) = padding[axis.axis.index()];
+                Slice::from(before..before + input.len_of(axis.axis))
+            })
+            .assign(input);
+        Ok(self.push(value, Operation::Pad(a, padding.to_vec())))
+    }
+
+    pub fn reshape(&mut self, a: Var, shape: &[usize]) -> Result<Var, Box<dyn Error>> {
+        let value = self
+            .value(a)
+            .as_standard_layout()
+            .into_shape(IxDyn(shape))?
+            .to_owned();
+        Ok(self.push(value, Operation::Reshape(a)))
+    }
+
+    // Joins variables along axis, every other axis must have the same length
+    pub fn concat(&mut self, vars: &[Var], axis: usize) -> Result<Var, Box<dyn Error>> {
+        let first = vars.first().ok_or(TapeError::EmptyAxisError)?;
+        self.check_axis(*first, axis)?;
+        let views: Vec<_> = vars.iter().map(|var| self.value(*var).view()).collect();
+        let value =
+            ndarray::concatenate(Axis(axis), &views).map_err(|_| TapeError::ShapeMismatchError)?;
+        Ok(self.push(value, Operation::Concat(vars.to_vec(), axis)))
+    }
+
+    fn check_axis(&self, a: Var, axis: usize) -> Result<(), Box<dyn Error>> {
+        if axis < self.value(a).ndim() {
+            Ok(())
+        } else {
+            Err(Box::new(TapeError::InvalidAxisError))
+        }
+    }
+}
+
+impl Tape {
+    // Gradients of the sum of output with respect to every variable
+    pub fn backward(&self, output: Var) -> Result<Gradients, Box<dyn Error>> {
+        self.backward_with(output, Array::ones(self.value(output).raw_dim()))
+    }
+
+    // Gradients given the upstream gradient of output, e.g. the gradient a
+    // layer receives from the next one
+    pub fn backward_with(
+        &self,
+        output: Var,
+        grad_output: ArrayD<f32>,
+    ) -> Result<Gradients, Box<dyn Error>> {
+        if grad_output.shape() != self.value(output).shape() {
+            return Err(Box::new(TapeError::ShapeMismatchError));
+        }
+
+        let mut gradients: Vec<Option<ArrayD<f32>>> = vec![None; self.nodes.len()];
+        gradients[output.0] = Some(grad_output);
+
+        for index in (0..=output.0).rev() {
+            let Some(grad) = gradients[index].take() else {
+                continue;
+            };
+            let node = &self.nodes[index];
+            for (input, input_grad) in self.input_gradients(node, &grad)? {
+                match &mut gradients[input.0] {
+                    Some(accumulated) => *accumulated += &input_grad,
+                    empty => *empty = Some(input_grad),
+                }
+            }
+            gradients[index] = Some(grad);
+        }
+
+        Ok(Gradients { gradients })
+    }
+
+    #[expect(clippy::type_complexity)]
+    fn input_gradients(
+        &self,
+        node: &Node,
+        grad: &ArrayD<f32>,
+    ) -> Result<Vec<(Var, ArrayD<f32>)>, Box<dyn Error>> {
+        let output = &node.value;
+        let gradients = match node.operation {
+            Operation::Leaf => Vec::new(),
+            Operation::Add(a, b) => vec![
+                (a, self.reduce_to(a, grad.clone())),
+                (b, self.reduce_to(b, grad.clone())),
+            ],
+            Operation::Sub(a, b) => vec![
+                (a, self.reduce_to(a, grad.clone())),
+                (b, self.reduce_to(b, -grad)),
+            ],
+            Operation::Mul(a, b) => vec![
+                (a, self.reduce_to(a, grad * self.value(b))),
+                (b, self.reduce_to(b, grad * self.value(a))),
+            ],
+            Operation::Div(a, b) => {
+                let b_value = self.value(b);
+                let grad_b = -(grad * self.value(a)) / (b_value * b_value);
+                vec![
+                    (a, self.reduce_to(a, grad / b_value)),
+                    (b, self.reduce_to(b, grad_b)),
+                ]
+            }
+            Operation::Scale(a, factor) => vec![(a, grad * factor)],
+            Operation::AddScalar(a) => vec![(a, grad.clone())],
+            Operation::MatMul(a, b) => {
+                let grad_matrix = grad.view().into_dimensionality::<Ix2>()?;
+                let a_matrix = self.value(a).view().into_dimensionality::<Ix2>()?;
+                let b_matrix = self.value(b).view().into_dimensionality::<Ix2>()?;
+                vec![
+                    (a, grad_matrix.dot(&b_matrix.t()).into_dyn()),
+                    (b, a_matrix.t().dot(&grad_matrix).into_dyn()),
+                ]
+            }
+            Operation::Transpose(a) => vec![(a, grad.clone().reversed_axes())],
+            Operation::Exp(a) => vec![(a, grad * output)],
+            Operation::Ln(a) => vec![(a, grad / self.value(a))],
+            Operation::Sqrt(a) => vec![(a, grad / &(output * 2.0))],
+            Operation::Powi(a, exponent) => {
+                #[expect(clippy::cast_precision_loss)]
+                let factor = exponent as f32;
+                let derivative = self.value(a).mapv(|x| factor * x.powi(exponent - 1));
+                vec![(a, grad * &derivative)]
+            }
+            Operation::Relu(a) => vec![(a, self.rectified_gradient(a, grad, 0.0))],
+            Operation::LeakyRelu(a, alpha) => vec![(a, self.rectified_gradient(a, grad, alpha))],
+            Operation::Sigmoid(a) => vec![(a, grad * &output.mapv(|s| s * (1.0 - s)))],
+            Operation::Tanh(a) => vec![(a, grad * &output.mapv(|t| 1.0 - t * t))],
+            Operation::Sum(a) | Operation::SumAxis(a, _) => {
+                vec![(a, self.broadcast_to(a, grad)?)]
+            }
+            Operation::MeanAxis(a, axis) => {
+                #[expect(clippy::cast_precision_loss)]
+                let len = self.value(a).len_of(Axis(axis)) as f32;
+                vec![(a, self.broadcast_to(a, grad)? / len)]
+            }
+            Operation::Softmax(a, axis) => {
+                let weighted_sum = (grad * output).sum_axis(Axis(axis)).insert_axis(Axis(axis));
+                vec![(a, output * &(grad - &weighted_sum))]
+            }
+            Operation::Slice(a, ref ranges) => {
+                let mut input_grad = Array::zeros(self.value(a).raw_dim());
+                input_grad
+                    .slice_each_axis_mut(|axis| {
+                        let (start, end) = ranges[axis.axis.index()];
+                        Slice::from(start..end)
+                    })
+                    .assign(grad);
+                vec![(a, input_grad)]
+            }
+            Operation::Pad(a, ref padding) => {
+                let input_shape = self.value(a).shape();
+                let input_grad = grad
+                    .slice_each_axis(|axis| {
+                        let (before, _) = padding[axis.axis.index()];
+                        Slice::from(before..before + input_shape[axis.axis.index()])
+                    })
+                    .to_owned();
+                vec![(a, input_grad)]
+            }
+            Operation::Reshape(a) => {
+                let input_grad = grad
+                    .as_standard_layout()
+                    .into_shape(self.value(a).raw_dim())?
+                    .to_owned();
+                vec![(a, input_grad)]
+            }
+            Operation::Concat(ref vars, axis) => self.concat_gradients(vars, axis, grad),
+        };
+        Ok(gradients)
+    }
+
+    // Gradient of relu-like operations, scaled by slope where var is negative
+    fn rectified_gradient(&self, var: Var, grad: &ArrayD<f32>, slope: f32) -> ArrayD<f32> {
+        let mut input_grad = grad.clone();
+        Zip::from(&mut input_grad)
+            .and(self.value(var))
+            .for_each(|g, x| *g = if *x > 0.0 { *g } else { slope * *g });
+        input_grad
+    }
+
+    // Splits the gradient of a concatenation back into its inputs
+    fn concat_gradients(
+        &self,
+        vars: &[Var],
+        axis: usize,
+        grad: &ArrayD<f32>,
+    ) -> Vec<(Var, ArrayD<f32>)> {
+        let mut start = 0;
+        vars.iter()
+            .map(|var| {
+                let len = self.value(*var).len_of(Axis(axis));
+                let input_grad = grad
+                    .slice_axis(Axis(axis), Slice::from(start..start + len))
+                    .to_owned();
+                start += len;
+                (*var, input_grad)
+            })
+            .collect()
+    }
+
+    // Sums a broadcast gradient back to the shape of var
+    fn reduce_to(&self, var: Var, mut grad: ArrayD<f32>) -> ArrayD<f32> {
+        let shape = self.value(var).shape();
+        while grad.ndim() > shape.len() {
+            grad = grad.sum_axis(Axis(0));
+        }
+        for (axis, len) in shape.iter().enumerate() {
+            if *len == 1 && grad.len_of(Axis(axis)) != 1 {
+                grad = grad.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+            }
+        }
+        grad
+    }
+
+    fn broadcast_to(&self, var: Var, grad: &ArrayD<f32>) -> Result<ArrayD<f32>, Box<dyn Error>> {
+        let shape = self.value(var).raw_dim();
+        Ok(grad
+            .broadcast(shape)
+            .ok_or(TapeError::ShapeMismatchError)?
+            .to_owned())
+    }
+}
+
+// Forward pass of one sample recorded on its own tape, so that backward can
+// be derived automatically
+pub struct TapeRecord {
+    pub tape: Tape,
+    pub input: Var,
+    pub parameters: Vec<Var>,
+    pub output: Var,
+}
+
+impl TapeRecord {
+    pub fn output(&self) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        Ok(self
+            .tape
+            .value(self.output)
+            .clone()
+            .into_dimensionality::<Ix3>()?)
+    }
+
+    // Gradient of the input and of every parameter, zeros for the ones the
+    // output does not depend on
+    #[expect(clippy::type_complexity)]
+    pub fn backward(
+        &self,
+        grad_output: &Array<f32, Ix3>,
+    ) -> Result<(Array<f32, Ix3>, Vec<Array<f32, Ix3>>), Box<dyn Error>> {
+        let gradients = self
+            .tape
+            .backward_with(self.output, grad_output.clone().into_dyn())?;
+        let gradient_of = |var: Var| -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+            let gradient = match gradients.get(var) {
+                Some(gradient) => gradient.clone(),
+                None => ArrayD::zeros(self.tape.value(var).raw_dim()),
+            };
+            Ok(gradient.into_dimensionality::<Ix3>()?)
+        };
+
+        let grad_input = gradient_of(self.input)?;
+        let grad_parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| gradient_of(*parameter))
+            .collect::<Result<_, _>>()?;
+        Ok((grad_input, grad_parameters))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TapeError {
+    #[error("operand shapes are not compatible")]
+    ShapeMismatchError,
+    #[error("axis is out of bounds")]
+    InvalidAxisError,
+    #[error("cannot reduce an empty axis")]
+    EmptyAxisError,
+    #[error("ranges should cover every axis and stay inside it")]
+    InvalidRangeError,
+}
@@ -0,0 +1,51 @@
+use std::{error::Error, ops::ControlFlow, path::PathBuf};
+
+use crate::{
+    callback::{Callback, EpochLogs},
+    model::sequential::SequentialModel,
+};
+
+// Saves the model weights every period epochs. An "{epoch}" in the path is
+// replaced by the epoch number (counted from 1), otherwise the file is
+// overwritten on every save.
+pub struct ModelCheckpoint {
+    pub path: String,
+    pub period: usize,
+    // Only save when the monitored loss is the best seen so far
+    pub save_best_only: bool,
+    best_loss: f32,
+}
+
+impl ModelCheckpoint {
+    pub fn new(path: String, period: Option<usize>, save_best_only: bool) -> Self {
+        Self {
+            path,
+            period: period.unwrap_or(1).max(1),
+            save_best_only,
+            best_loss: f32::INFINITY,
+        }
+    }
+
+    pub fn path_for_epoch(&self, epoch: usize) -> PathBuf {
+        PathBuf::from(self.path.replace("{epoch}", &(epoch + 1).to_string()))
+    }
+}
+
+impl Callback for ModelCheckpoint {
+    fn on_epoch_end(
+        &mut self,
+        model: &mut SequentialModel,
+        logs: &EpochLogs,
+    ) -> Result<ControlFlow<()>, Box<dyn Error>> {
+        if !(logs.epoch + 1).is_multiple_of(self.period) {
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        let monitored_loss = logs.monitored_loss();
+        if !self.save_best_only || monitored_loss < self.best_loss {
+            self.best_loss = self.best_loss.min(monitored_loss);
+            model.save_weights(&self.path_for_epoch(logs.epoch))?;
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+}
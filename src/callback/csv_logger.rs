@@ -0,0 +1,64 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    ops::ControlFlow,
+    path::PathBuf,
+};
+
+use crate::{
+    callback::{Callback, EpochLogs},
+    model::sequential::SequentialModel,
+};
+
+// Writes one line of metrics per epoch, the file is created when training
+// begins
+pub struct CsvLogger {
+    pub path: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl CsvLogger {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, writer: None }
+    }
+}
+
+impl Callback for CsvLogger {
+    fn on_train_begin(&mut self, _model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        writeln!(writer, "epoch,loss,validation_loss,learning_rate")?;
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn on_epoch_end(
+        &mut self,
+        _model: &mut SequentialModel,
+        logs: &EpochLogs,
+    ) -> Result<ControlFlow<()>, Box<dyn Error>> {
+        if let Some(writer) = &mut self.writer {
+            let validation_loss = logs
+                .validation_loss
+                .map(|loss| loss.to_string())
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{},{},{validation_loss},{}",
+                logs.epoch + 1,
+                logs.loss,
+                logs.learning_rate
+            )?;
+            // keep the file readable while training is still running
+            writer.flush()?;
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn on_train_end(&mut self, _model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use std::{error::Error, ops::ControlFlow};
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    callback::{Callback, EpochLogs},
+    model::sequential::SequentialModel,
+};
+
+// Stops training once the monitored loss has not improved by at least
+// min_delta for patience epochs
+pub struct EarlyStopping {
+    pub patience: usize,
+    pub min_delta: f32,
+    // Puts back the weights of the best epoch when training ends
+    pub restore_best_weights: bool,
+    best_loss: f32,
+    epochs_without_improvement: usize,
+    best_weights: Option<Vec<Array<f32, Ix3>>>,
+    stopped_epoch: Option<usize>,
+}
+
+impl EarlyStopping {
+    pub fn new(patience: usize, min_delta: Option<f32>, restore_best_weights: bool) -> Self {
+        Self {
+            patience,
+            min_delta: min_delta.unwrap_or(0.0),
+            restore_best_weights,
+            best_loss: f32::INFINITY,
+            epochs_without_improvement: 0,
+            best_weights: None,
+            stopped_epoch: None,
+        }
+    }
+
+    // Epoch at which training was stopped, None if it ran every epoch
+    pub fn stopped_epoch(&self) -> Option<usize> {
+        self.stopped_epoch
+    }
+
+    pub fn best_loss(&self) -> f32 {
+        self.best_loss
+    }
+}
+
+impl Callback for EarlyStopping {
+    fn on_train_begin(&mut self, _model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        self.best_loss = f32::INFINITY;
+        self.epochs_without_improvement = 0;
+        self.best_weights = None;
+        self.stopped_epoch = None;
+        Ok(())
+    }
+
+    fn on_epoch_end(
+        &mut self,
+        model: &mut SequentialModel,
+        logs: &EpochLogs,
+    ) -> Result<ControlFlow<()>, Box<dyn Error>> {
+        let monitored_loss = logs.monitored_loss();
+        if monitored_loss < self.best_loss - self.min_delta {
+            self.best_loss = monitored_loss;
+            self.epochs_without_improvement = 0;
+            if self.restore_best_weights {
+                self.best_weights = Some(model.weights());
+            }
+            return Ok(ControlFlow::Continue(()));
+        }
+
+        self.epochs_without_improvement += 1;
+        if self.epochs_without_improvement >= self.patience {
+            self.stopped_epoch = Some(logs.epoch);
+            return Ok(ControlFlow::Break(()));
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn on_train_end(&mut self, model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        if let Some(best_weights) = &self.best_weights {
+            model.set_weights(best_weights)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+// training callbacks
+//
+// Callbacks are given to SequentialModel::fit_with_callbacks and called around
+// every epoch and batch with the model being trained. Returning
+// ControlFlow::Break from on_epoch_end stops the training.
+
+pub mod checkpoint;
+pub mod csv_logger;
+pub mod early_stopping;
+pub mod progress_bar;
+
+use std::{error::Error, ops::ControlFlow};
+
+use crate::model::sequential::SequentialModel;
+
+// Epochs and batches are counted from 0
+#[derive(Debug, Clone, Copy)]
+pub struct BatchLogs {
+    pub epoch: usize,
+    pub epochs: usize,
+    pub batch: usize,
+    pub batches: usize,
+    // Mean loss of the batch samples
+    pub loss: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EpochLogs {
+    pub epoch: usize,
+    pub epochs: usize,
+    pub loss: f32,
+    pub validation_loss: Option<f32>,
+    // Learning rate the next epoch starts with
+    pub learning_rate: f32,
+}
+
+impl EpochLogs {
+    // Validation loss when fit has a validation split, training loss otherwise
+    pub fn monitored_loss(&self) -> f32 {
+        self.validation_loss.unwrap_or(self.loss)
+    }
+}
+
+pub trait Callback {
+    fn on_train_begin(&mut self, _model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_train_end(&mut self, _model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_epoch_begin(
+        &mut self,
+        _model: &mut SequentialModel,
+        _epoch: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_epoch_end(
+        &mut self,
+        _model: &mut SequentialModel,
+        _logs: &EpochLogs,
+    ) -> Result<ControlFlow<()>, Box<dyn Error>> {
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn on_batch_begin(
+        &mut self,
+        _model: &mut SequentialModel,
+        _batch: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_batch_end(
+        &mut self,
+        _model: &mut SequentialModel,
+        _logs: &BatchLogs,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
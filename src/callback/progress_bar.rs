@@ -0,0 +1,67 @@
+use std::{
+    error::Error,
+    io::{stderr, Write},
+    ops::ControlFlow,
+};
+
+use crate::{
+    callback::{BatchLogs, Callback, EpochLogs},
+    model::sequential::SequentialModel,
+};
+
+// Redraws a progress bar on stderr after every batch and prints the epoch
+// metrics when it ends
+pub struct ProgressBar {
+    pub width: usize,
+}
+
+impl ProgressBar {
+    pub fn new(width: Option<usize>) -> Self {
+        Self {
+            width: width.unwrap_or(30),
+        }
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Callback for ProgressBar {
+    fn on_batch_end(
+        &mut self,
+        _model: &mut SequentialModel,
+        logs: &BatchLogs,
+    ) -> Result<(), Box<dyn Error>> {
+        let done = self.width * (logs.batch + 1) / logs.batches.max(1);
+        let bar = format!("{}{}", "=".repeat(done), " ".repeat(self.width - done));
+        let mut stderr = stderr().lock();
+        write!(
+            stderr,
+            "\rEpoch {}/{} [{bar}] {}/{} loss: {:.4}",
+            logs.epoch + 1,
+            logs.epochs,
+            logs.batch + 1,
+            logs.batches,
+            logs.loss
+        )?;
+        stderr.flush()?;
+        Ok(())
+    }
+
+    fn on_epoch_end(
+        &mut self,
+        _model: &mut SequentialModel,
+        logs: &EpochLogs,
+    ) -> Result<ControlFlow<()>, Box<dyn Error>> {
+        let mut stderr = stderr().lock();
+        write!(stderr, " - epoch loss: {:.4}", logs.loss)?;
+        if let Some(validation_loss) = logs.validation_loss {
+            write!(stderr, " - validation loss: {validation_loss:.4}")?;
+        }
+        writeln!(stderr, " - learning rate: {:e}", logs.learning_rate)?;
+        Ok(ControlFlow::Continue(()))
+    }
+}
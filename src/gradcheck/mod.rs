@@ -0,0 +1,140 @@
+// finite-difference gradient checking
+//
+// Compares the gradients given by Layer::backward with central differences of
+// the loss sum(output * projection), where projection is a fixed random
+// array. The default step is large for finite differences because smaller
+// ones drown in f32 rounding. Perturbed losses are computed with
+// forward_train, so stochastic layers should be configured to be
+// deterministic before being checked.
+
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+use ndarray_rand::RandomExt;
+use rand::{distributions::Uniform, Rng};
+
+use crate::layer::Layer;
+
+// Keeps the relative error meaningful when both gradients are close to zero,
+// where f32 finite differences are dominated by rounding
+const RELATIVE_ERROR_FLOOR: f32 = 1e-2;
+
+#[derive(Debug, Clone)]
+pub struct TensorCheck {
+    // "input {sample}" or "parameter {index}", parameters being in the order
+    // of Layer::parameters
+    pub name: String,
+    pub max_relative_error: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GradientCheckReport {
+    pub tensors: Vec<TensorCheck>,
+}
+
+impl GradientCheckReport {
+    pub fn max_relative_error(&self) -> f32 {
+        self.tensors
+            .iter()
+            .map(|tensor| tensor.max_relative_error)
+            .fold(0.0, f32::max)
+    }
+
+    pub fn passes(&self, tolerance: f32) -> bool {
+        self.max_relative_error() <= tolerance
+    }
+}
+
+pub fn check_gradients(
+    layer: &mut Layer,
+    inputs: &[Array<f32, Ix3>],
+    epsilon: Option<f32>,
+) -> Result<GradientCheckReport, Box<dyn Error>> {
+    check_gradients_using(layer, inputs, epsilon, &mut rand::thread_rng())
+}
+
+// Same as check_gradients, drawing the projections from rng. With a seeded
+// rng and seeded layers the check gives the same report on every run.
+pub fn check_gradients_using<R: Rng + ?Sized>(
+    layer: &mut Layer,
+    inputs: &[Array<f32, Ix3>],
+    epsilon: Option<f32>,
+    rng: &mut R,
+) -> Result<GradientCheckReport, Box<dyn Error>> {
+    let epsilon = epsilon.unwrap_or(1e-2);
+
+    let outputs = layer.forward_train(inputs)?;
+    let projections: Vec<_> = outputs
+        .iter()
+        .map(|output| Array::random_using(output.raw_dim(), Uniform::new(-1.0, 1.0), rng))
+        .collect();
+    layer.zero_gradients();
+    let input_gradients = layer.backward(&projections)?;
+    let parameter_gradients: Vec<_> = layer
+        .parameters_mut()
+        .into_iter()
+        .map(|parameter| parameter.gradient.clone())
+        .collect();
+
+    let mut report = GradientCheckReport::default();
+
+    for (sample, analytic) in input_gradients.iter().enumerate() {
+        let mut max_relative_error: f32 = 0.0;
+        for (index, analytic_value) in analytic.indexed_iter() {
+            let mut perturbed = inputs.to_vec();
+            perturbed[sample][index] += epsilon;
+            let loss_plus = projected_loss(layer, &perturbed, &projections)?;
+            perturbed[sample][index] -= 2.0 * epsilon;
+            let loss_minus = projected_loss(layer, &perturbed, &projections)?;
+
+            let numeric = (loss_plus - loss_minus) / (2.0 * epsilon);
+            max_relative_error = max_relative_error.max(relative_error(*analytic_value, numeric));
+        }
+        report.tensors.push(TensorCheck {
+            name: format!("input {sample}"),
+            max_relative_error,
+        });
+    }
+
+    for (parameter_index, analytic) in parameter_gradients.iter().enumerate() {
+        let mut max_relative_error: f32 = 0.0;
+        for (index, analytic_value) in analytic.indexed_iter() {
+            layer.parameters_mut()[parameter_index].value[index] += epsilon;
+            let loss_plus = projected_loss(layer, inputs, &projections)?;
+            layer.parameters_mut()[parameter_index].value[index] -= 2.0 * epsilon;
+            let loss_minus = projected_loss(layer, inputs, &projections)?;
+            layer.parameters_mut()[parameter_index].value[index] += epsilon;
+
+            let numeric = (loss_plus - loss_minus) / (2.0 * epsilon);
+            max_relative_error = max_relative_error.max(relative_error(*analytic_value, numeric));
+        }
+        report.tensors.push(TensorCheck {
+            name: format!("parameter {parameter_index}"),
+            max_relative_error,
+        });
+    }
+
+    // leave the layer with the gradients of the unperturbed inputs
+    layer.forward_train(inputs)?;
+    layer.zero_gradients();
+    layer.backward(&projections)?;
+
+    Ok(report)
+}
+
+fn projected_loss(
+    layer: &mut Layer,
+    inputs: &[Array<f32, Ix3>],
+    projections: &[Array<f32, Ix3>],
+) -> Result<f32, Box<dyn Error>> {
+    let outputs = layer.forward_train(inputs)?;
+    Ok(outputs
+        .iter()
+        .zip(projections)
+        .map(|(output, projection)| (output * projection).sum())
+        .sum())
+}
+
+fn relative_error(analytic: f32, numeric: f32) -> f32 {
+    (analytic - numeric).abs() / (analytic.abs() + numeric.abs()).max(RELATIVE_ERROR_FLOOR)
+}
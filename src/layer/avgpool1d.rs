@@ -0,0 +1,97 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Axis, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{maxpool1d::Pool1dError, util::sequence_window, Padding1d},
+};
+
+// Average pooling over (length, 1, channels) sequences. Windows only average
+// the steps they cover in the input, so padding does not drag values to zero.
+pub struct AvgPool1dLayer {
+    pub pool_size: usize,
+    pub strides: usize,
+    pub padding: Padding1d,
+    input_lengths: Vec<usize>,
+}
+
+impl AvgPool1dLayer {
+    pub fn new(pool_size: usize, strides: Option<usize>, padding: Option<Padding1d>) -> Self {
+        Self {
+            pool_size,
+            strides: strides.unwrap_or(pool_size),
+            padding: padding.unwrap_or_default(),
+            input_lengths: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let (input_length, _, channels) = input_dim;
+        let output_length = self
+            .padding
+            .output_length(input_length, self.pool_size, self.strides);
+        (output_length, 1, channels)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (length, width, channels) = input.dim();
+        if width != 1 {
+            return Err(Box::new(Pool1dError::InvalidDimensionsError));
+        }
+        let (before, _) = self.padding.amounts(length, self.pool_size, self.strides);
+        let (output_length, _, _) = self.output_dim(input.dim());
+
+        let mut output = Array::zeros((output_length, 1, channels));
+        for step in 0..output_length {
+            let steps = sequence_window(step, self.pool_size, self.strides, before, length);
+            let window = input.slice(s![steps, 0, ..]);
+            output.slice_mut(s![step, 0, ..]).assign(
+                &window
+                    .mean_axis(Axis(0))
+                    .ok_or(Pool1dError::EmptyWindowError)?,
+            );
+        }
+        Ok(output)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.input_lengths = inputs.iter().map(|input| input.len_of(Axis(0))).collect();
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    // Spreads each upstream gradient evenly over the steps of its window
+    #[expect(clippy::cast_precision_loss)]
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.input_lengths.len() {
+            return Err(Box::new(Pool1dError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (&length, grad_output) in self.input_lengths.iter().zip(grad_outputs) {
+            let channels = grad_output.len_of(Axis(2));
+            if grad_output.dim() != self.output_dim((length, 1, channels)) {
+                return Err(Box::new(Pool1dError::GradientShapeError));
+            }
+            let (before, _) = self.padding.amounts(length, self.pool_size, self.strides);
+            let mut grad_input = Array::zeros((length, 1, channels));
+            for (step, grad) in grad_output.outer_iter().enumerate() {
+                let steps = sequence_window(step, self.pool_size, self.strides, before, length);
+                let window_size = steps.len() as f32;
+                let mut window = grad_input.slice_mut(s![steps, .., ..]);
+                window += &(&grad / window_size);
+            }
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+}
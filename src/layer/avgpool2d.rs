@@ -0,0 +1,118 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Axis, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::util::{add_padding, pool_output_dim, pool_windows, remove_padding},
+};
+
+// Same windows as MaxPool2dLayer, averaging them instead. Padding cells
+// count as zeros in the average.
+pub struct AvgPool2dLayer {
+    pub pool_size: (usize, usize),
+    pub strides: (usize, usize),
+    pub padding: (usize, usize),
+    input_dims: Vec<(usize, usize, usize)>,
+}
+
+impl AvgPool2dLayer {
+    pub fn new(
+        pool_size: (usize, usize),
+        strides: Option<(usize, usize)>,
+        padding: Option<(usize, usize)>,
+    ) -> Self {
+        let strides = strides.unwrap_or(pool_size);
+        let padding = padding.unwrap_or((0, 0));
+        Self {
+            pool_size,
+            strides,
+            padding,
+            input_dims: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        pool_output_dim(input_dim, self.pool_size, self.strides, self.padding)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let input_padded = add_padding(input, &self.padding);
+        let output_dim = self.output_dim(input.dim());
+        let mut output = Array::zeros(output_dim);
+
+        for ((output_row, output_col), (row, col)) in pool_windows(output_dim, self.strides) {
+            let window = input_padded.slice(s![
+                row..row + self.pool_size.0,
+                col..col + self.pool_size.1,
+                ..
+            ]);
+            let sums = window.sum_axis(Axis(0)).sum_axis(Axis(0));
+            output
+                .slice_mut(s![output_row, output_col, ..])
+                .assign(&(sums / self.window_size()));
+        }
+        Ok(output)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.input_dims = inputs.iter().map(Array::dim).collect();
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    // Spreads each upstream gradient evenly over the cells of its window
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.input_dims.len() {
+            return Err(Box::new(AvgPool2dError::MissingForwardError));
+        }
+        let window_size = self.window_size();
+
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (&input_dim, grad_output) in self.input_dims.iter().zip(grad_outputs) {
+            let output_dim = self.output_dim(input_dim);
+            if grad_output.dim() != output_dim {
+                return Err(Box::new(AvgPool2dError::GradientShapeError));
+            }
+            let (input_height, input_width, input_channels) = input_dim;
+            let mut grad_input_padded = Array::zeros((
+                input_height + 2 * self.padding.0,
+                input_width + 2 * self.padding.1,
+                input_channels,
+            ));
+            for ((output_row, output_col), (row, col)) in pool_windows(output_dim, self.strides) {
+                let grad = grad_output.slice(s![output_row, output_col, ..]);
+                let mut window = grad_input_padded.slice_mut(s![
+                    row..row + self.pool_size.0,
+                    col..col + self.pool_size.1,
+                    ..
+                ]);
+                window += &(&grad / window_size);
+            }
+            grad_inputs.push(remove_padding(&grad_input_padded, &self.padding));
+        }
+        Ok(grad_inputs)
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    fn window_size(&self) -> f32 {
+        (self.pool_size.0 * self.pool_size.1) as f32
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AvgPool2dError {
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -0,0 +1,232 @@
+use std::error::Error;
+
+use ndarray::{Array, Axis, Ix3};
+
+use crate::{activation::ActivationFunctionType, layer::Parameter};
+
+// Normalizes every feature with the mean and variance of the batch while
+// training, and with running averages of them at inference. Features are the
+// channels of (height, width, channels) Conv2d outputs or the values of
+// (1, features, 1) Dense outputs.
+pub struct BatchNormLayer {
+    pub features: usize,
+    pub feature_axis: usize,
+    // Weight of the running statistics when a new batch is averaged in
+    pub momentum: f32,
+    pub epsilon: f32,
+    pub gamma: Array<f32, Ix3>,
+    pub beta: Array<f32, Ix3>,
+    pub running_mean: Array<f32, Ix3>,
+    pub running_variance: Array<f32, Ix3>,
+    pub gamma_gradient: Array<f32, Ix3>,
+    pub beta_gradient: Array<f32, Ix3>,
+    cache: Option<BatchNormCache>,
+}
+
+// Values kept by forward_train for the whole batch
+struct BatchNormCache {
+    normalized: Vec<Array<f32, Ix3>>,
+    inverse_std: Array<f32, Ix3>,
+}
+
+impl BatchNormLayer {
+    pub fn for_conv2d(channels: usize, momentum: Option<f32>, epsilon: Option<f32>) -> Self {
+        Self::new(channels, 2, (1, 1, channels), momentum, epsilon)
+    }
+
+    pub fn for_dense(features: usize, momentum: Option<f32>, epsilon: Option<f32>) -> Self {
+        Self::new(features, 1, (1, features, 1), momentum, epsilon)
+    }
+
+    fn new(
+        features: usize,
+        feature_axis: usize,
+        parameter_dim: (usize, usize, usize),
+        momentum: Option<f32>,
+        epsilon: Option<f32>,
+    ) -> Self {
+        Self {
+            features,
+            feature_axis,
+            momentum: momentum.unwrap_or(0.99),
+            epsilon: epsilon.unwrap_or(1e-3),
+            gamma: Array::ones(parameter_dim),
+            beta: Array::zeros(parameter_dim),
+            running_mean: Array::zeros(parameter_dim),
+            running_variance: Array::ones(parameter_dim),
+            gamma_gradient: Array::zeros(parameter_dim),
+            beta_gradient: Array::zeros(parameter_dim),
+            cache: None,
+        }
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    // Uses the running statistics
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.check_input(input)?;
+        let inverse_std = self
+            .running_variance
+            .mapv(|variance| 1.0 / (variance + self.epsilon).sqrt());
+        Ok((input - &self.running_mean) * &inverse_std * &self.gamma + &self.beta)
+    }
+
+    // Normalizes with the statistics of the batch and updates the running
+    // ones
+    #[expect(clippy::cast_precision_loss)]
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        for input in inputs {
+            self.check_input(input)?;
+        }
+        let count = self.values_per_feature(inputs) as f32;
+        if count == 0.0 {
+            return Err(Box::new(BatchNormError::EmptyBatchError));
+        }
+
+        let mean = self.sum_per_feature(inputs.iter().cloned()) / count;
+        let variance = self
+            .sum_per_feature(inputs.iter().map(|input| (input - &mean).mapv(|x| x * x)))
+            / count;
+        let inverse_std = variance.mapv(|variance| 1.0 / (variance + self.epsilon).sqrt());
+
+        self.running_mean = &self.running_mean * self.momentum + &mean * (1.0 - self.momentum);
+        self.running_variance =
+            &self.running_variance * self.momentum + &variance * (1.0 - self.momentum);
+
+        let normalized: Vec<_> = inputs
+            .iter()
+            .map(|input| (input - &mean) * &inverse_std)
+            .collect();
+        let outputs = normalized
+            .iter()
+            .map(|x_hat| x_hat * &self.gamma + &self.beta)
+            .collect();
+        self.cache = Some(BatchNormCache {
+            normalized,
+            inverse_std,
+        });
+        Ok(outputs)
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        let Some(cache) = &self.cache else {
+            return Err(Box::new(BatchNormError::MissingForwardError));
+        };
+        if grad_outputs.len() != cache.normalized.len() {
+            return Err(Box::new(BatchNormError::MissingForwardError));
+        }
+        let count = self.values_per_feature(grad_outputs) as f32;
+
+        let grad_beta = self.sum_per_feature(grad_outputs.iter().cloned());
+        let grad_gamma = self.sum_per_feature(
+            grad_outputs
+                .iter()
+                .zip(&cache.normalized)
+                .map(|(grad_output, x_hat)| grad_output * x_hat),
+        );
+
+        // dx = inverse_std / m * (m * dx_hat - sum(dx_hat) - x_hat * sum(dx_hat * x_hat)),
+        // where dx_hat = gamma * dy
+        let grad_inputs = grad_outputs
+            .iter()
+            .zip(&cache.normalized)
+            .map(|(grad_output, x_hat)| {
+                let grad_normalized = grad_output * &self.gamma;
+                (grad_normalized * count
+                    - &(&grad_beta * &self.gamma)
+                    - x_hat * &(&grad_gamma * &self.gamma))
+                    * &cache.inverse_std
+                    / count
+            })
+            .collect();
+
+        self.beta_gradient += &grad_beta;
+        self.gamma_gradient += &grad_gamma;
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.gamma_gradient.fill(0.0);
+        self.beta_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.parameters_and_buffers_mut().0
+    }
+
+    pub fn buffers(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.running_mean, &self.running_variance]
+    }
+
+    pub fn parameters_and_buffers_mut(
+        &mut self,
+    ) -> (Vec<Parameter<'_>>, Vec<&mut Array<f32, Ix3>>) {
+        (
+            vec![
+                Parameter {
+                    value: &mut self.gamma,
+                    gradient: &self.gamma_gradient,
+                },
+                Parameter {
+                    value: &mut self.beta,
+                    gradient: &self.beta_gradient,
+                },
+            ],
+            vec![&mut self.running_mean, &mut self.running_variance],
+        )
+    }
+
+    fn check_input(&self, input: &Array<f32, Ix3>) -> Result<(), Box<dyn Error>> {
+        let is_dense_layout =
+            self.feature_axis != 1 || (input.shape()[0] == 1 && input.shape()[2] == 1);
+        if input.len_of(Axis(self.feature_axis)) == self.features && is_dense_layout {
+            Ok(())
+        } else {
+            Err(Box::new(BatchNormError::InvalidDimensionsError))
+        }
+    }
+
+    // Values of every feature in the whole batch, whose Conv2d samples may
+    // differ in height and width
+    fn values_per_feature(&self, inputs: &[Array<f32, Ix3>]) -> usize {
+        inputs.iter().map(|input| input.len() / self.features).sum()
+    }
+
+    // Sum over the batch and every axis but the feature one
+    fn sum_per_feature(&self, arrays: impl Iterator<Item = Array<f32, Ix3>>) -> Array<f32, Ix3> {
+        let mut sum = Array::zeros(self.gamma.raw_dim());
+        for array in arrays {
+            let mut reduced = array;
+            for axis in 0..3 {
+                if axis != self.feature_axis {
+                    reduced = reduced.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+                }
+            }
+            sum += &reduced;
+        }
+        sum
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BatchNormError {
+    #[error("input features should match the layer features")]
+    InvalidDimensionsError,
+    #[error("batch normalization needs at least one sample")]
+    EmptyBatchError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
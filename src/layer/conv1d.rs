@@ -0,0 +1,270 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Axis, Ix2, Ix3, SliceInfo, SliceInfoElem};
+use ndarray_rand::RandomExt;
+use rand::distributions::Uniform;
+
+use crate::{
+    activation::{
+        activate, activation_backward, softmax, softmax_backward, ActivationFunctionType,
+    },
+    layer::{Padding1d, Parameter},
+};
+
+// Convolution over (length, 1, channels) sequences. Windows are gathered in
+// a (outputs, kernel_size * channels) matrix so that the whole sequence is
+// convolved with a single matrix product. The length may change between
+// samples.
+pub struct Conv1dLayer {
+    pub filters: usize,
+    pub kernel_size: usize,
+    pub channels: usize,
+    pub padding: Padding1d,
+    pub strides: usize,
+    pub dilation_rate: usize,
+    activation_function: ActivationFunctionType,
+    // (kernel_size, channels, filters)
+    kernels: Array<f32, Ix3>,
+    kernels_gradient: Array<f32, Ix3>,
+    cache: Vec<Conv1dCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct Conv1dCache {
+    input_length: usize,
+    columns: Array<f32, Ix2>,
+    preactivation: Array<f32, Ix3>,
+    output: Array<f32, Ix3>,
+}
+
+impl Conv1dLayer {
+    pub fn new(
+        filters: usize,
+        kernel_size: usize,
+        channels: usize,
+        padding: Option<Padding1d>,
+        strides: Option<usize>,
+        dilation_rate: Option<usize>,
+        activation_function_type: Option<ActivationFunctionType>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if kernel_size < 1 {
+            return Err(Box::new(Conv1dError::KernelSizeError));
+        }
+        let strides = strides.unwrap_or(1);
+        let dilation_rate = dilation_rate.unwrap_or(1);
+        if strides < 1 || dilation_rate < 1 {
+            return Err(Box::new(Conv1dError::StepError));
+        }
+
+        Ok(Self {
+            filters,
+            kernel_size,
+            channels,
+            padding: padding.unwrap_or_default(),
+            strides,
+            dilation_rate,
+            activation_function: activation_function_type.unwrap_or(ActivationFunctionType::None),
+            kernels: Array::random((kernel_size, channels, filters), Uniform::new(-1.0, 1.0)),
+            kernels_gradient: Array::zeros((kernel_size, channels, filters)),
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let output_length = self
+            .padding
+            .output_length(input_dim.0, self.extent(), self.strides);
+        (output_length, 1, self.filters)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.activation_function
+    }
+
+    pub fn kernels(&self) -> &Array<f32, Ix3> {
+        &self.kernels
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let columns = self.columns(input)?;
+        let preactivation = self.convolve(&columns)?;
+        Ok(self.activate(&preactivation))
+    }
+
+    // Same as forward, but keeps what backward needs for every sample of the
+    // batch. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let columns = self.columns(input)?;
+            let preactivation = self.convolve(&columns)?;
+            let output = self.activate(&preactivation);
+            outputs.push(output.clone());
+            self.cache.push(Conv1dCache {
+                input_length: input.len_of(Axis(0)),
+                columns,
+                preactivation,
+                output,
+            });
+        }
+        Ok(outputs)
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(Conv1dError::MissingForwardError));
+        }
+        let kernels = self
+            .kernels
+            .to_shape((self.kernel_size * self.channels, self.filters))?;
+
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            if grad_output.shape() != cache.output.shape() {
+                return Err(Box::new(Conv1dError::GradientShapeError));
+            }
+            let grad_preactivation =
+                self.activation_backward(&cache.preactivation, &cache.output, grad_output);
+            let grad_preactivation = grad_preactivation.index_axis(Axis(1), 0);
+
+            let batch_kernels_gradient = cache.columns.t().dot(&grad_preactivation);
+            self.kernels_gradient += &batch_kernels_gradient.to_shape(self.kernels.raw_dim())?;
+
+            // scatter the gradient of every window back to its input steps
+            let grad_columns = grad_preactivation.dot(&kernels.t());
+            let (before, after) =
+                self.padding
+                    .amounts(cache.input_length, self.extent(), self.strides);
+            let mut grad_input_padded =
+                Array::zeros((before + cache.input_length + after, 1, self.channels));
+            for (step, grad_window) in grad_columns.outer_iter().enumerate() {
+                let grad_window = grad_window.to_shape((self.kernel_size, 1, self.channels))?;
+                grad_input_padded
+                    .slice_mut(self.window(step))
+                    .scaled_add(1.0, &grad_window);
+            }
+            grad_inputs.push(
+                grad_input_padded
+                    .slice(s![before..before + cache.input_length, .., ..])
+                    .to_owned(),
+            );
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.kernels_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.kernels]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![Parameter {
+            value: &mut self.kernels,
+            gradient: &self.kernels_gradient,
+        }]
+    }
+
+    // Softmax runs over the filters of every output step on its own, the
+    // other activations are element-wise
+    fn activate(&self, preactivation: &Array<f32, Ix3>) -> Array<f32, Ix3> {
+        if self.activation_function != ActivationFunctionType::Softmax {
+            return activate(self.activation_function, preactivation);
+        }
+        let mut output = preactivation.clone();
+        for mut step in output.axis_chunks_iter_mut(Axis(0), 1) {
+            let step_output = softmax(&step.to_owned());
+            step.assign(&step_output);
+        }
+        output
+    }
+
+    fn activation_backward(
+        &self,
+        preactivation: &Array<f32, Ix3>,
+        output: &Array<f32, Ix3>,
+        grad_output: &Array<f32, Ix3>,
+    ) -> Array<f32, Ix3> {
+        if self.activation_function != ActivationFunctionType::Softmax {
+            return activation_backward(
+                self.activation_function,
+                preactivation,
+                output,
+                grad_output,
+            );
+        }
+        let mut grad_preactivation = grad_output.clone();
+        for (mut step, step_output) in grad_preactivation
+            .axis_chunks_iter_mut(Axis(0), 1)
+            .zip(output.axis_chunks_iter(Axis(0), 1))
+        {
+            let step_grad = softmax_backward(&step_output.to_owned(), &step.to_owned());
+            step.assign(&step_grad);
+        }
+        grad_preactivation
+    }
+
+    // Steps covered by one kernel, dilation included
+    fn extent(&self) -> usize {
+        (self.kernel_size - 1) * self.dilation_rate + 1
+    }
+
+    // Padded input steps read by the output step
+    fn window(&self, step: usize) -> SliceInfo<[SliceInfoElem; 3], Ix3, Ix3> {
+        let first = step * self.strides;
+        s![first..first + self.extent();self.dilation_rate, .., ..]
+    }
+
+    // One row per output step holding the padded window it reads
+    fn columns(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix2>, Box<dyn Error>> {
+        let (length, width, channels) = input.dim();
+        if width != 1 || channels != self.channels {
+            return Err(Box::new(Conv1dError::InvalidDimensionsError));
+        }
+        let (before, after) = self.padding.amounts(length, self.extent(), self.strides);
+        let mut input_padded = Array::zeros((before + length + after, 1, channels));
+        input_padded
+            .slice_mut(s![before..before + length, .., ..])
+            .assign(input);
+
+        let (output_length, _, _) = self.output_dim(input.dim());
+        let mut columns = Array::zeros((output_length, self.kernel_size * channels));
+        for (step, mut column) in columns.outer_iter_mut().enumerate() {
+            let window = input_padded.slice(self.window(step));
+            column.assign(&window.to_shape(self.kernel_size * channels)?);
+        }
+        Ok(columns)
+    }
+
+    fn convolve(&self, columns: &Array<f32, Ix2>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let kernels = self
+            .kernels
+            .to_shape((self.kernel_size * self.channels, self.filters))?;
+        let output = columns.dot(&kernels);
+        let output_length = output.len_of(Axis(0));
+        Ok(output.into_shape((output_length, 1, self.filters))?)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Conv1dError {
+    #[error("invalid kernel size")]
+    KernelSizeError,
+    #[error("strides and dilation rate should be at least 1")]
+    StepError,
+    #[error("input should be a (length, 1, channels) sequence with the layer channels")]
+    InvalidDimensionsError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -2,13 +2,17 @@
 use std::error::Error;
 use std::ops::Mul;
 
-use ndarray::{s, Array, Ix3, Zip};
+use ndarray::{s, Array, Axis, Ix3, SliceInfo, SliceInfoElem, Zip};
 use ndarray_rand::RandomExt;
 use rand::distributions::Uniform;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::{
-    activation::{leaky_relu, relu, sigmoid, tanh, ActivationFunctionType},
-    layer::util::add_padding,
+    activation::{activation_backward, leaky_relu, relu, sigmoid, tanh, ActivationFunctionType},
+    layer::{
+        util::{add_padding, remove_padding},
+        Parameter,
+    },
 };
 
 pub struct Conv2dLayer {
@@ -20,7 +24,17 @@ pub struct Conv2dLayer {
     pub output_dim: (usize, usize, usize),
     strides: (usize, usize),
     dilatation_rate: (usize, usize),
+    groups: usize,
     activation_function: ActivationFunctionType,
+    kernels_gradient: Vec<Array<f32, Ix3>>,
+    cache: Vec<Conv2dCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct Conv2dCache {
+    input_padded: Array<f32, Ix3>,
+    preactivation: Array<f32, Ix3>,
+    output: Array<f32, Ix3>,
 }
 
 impl Conv2dLayer {
@@ -63,48 +77,222 @@ impl Conv2dLayer {
             ),
             strides,
             dilatation_rate,
+            groups: 1,
             activation_function,
+            kernels_gradient: vec![Array::zeros((kernel_size, kernel_size, input_dim.2)); filters],
+            cache: Vec::new(),
         })
     }
 
+    // Splits channels and filters in `groups` independent convolutions, each
+    // filter only reading the channels of its group. groups == channels is a
+    // depthwise convolution.
+    pub fn with_groups(mut self, groups: usize) -> Result<Self, Box<dyn Error>> {
+        let channels = self.input_dim.2;
+        if groups < 1 || !channels.is_multiple_of(groups) || !self.filters.is_multiple_of(groups) {
+            return Err(Box::new(Conv2dError::GroupsError));
+        }
+        let group_channels = channels / groups;
+        self.groups = groups;
+        self.kernels = populate_kernels_with_random(self.kernel_size, self.filters, group_channels);
+        self.kernels_gradient =
+            vec![Array::zeros((self.kernel_size, self.kernel_size, group_channels)); self.filters];
+        Ok(self)
+    }
+
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    // Whether an input of shape input_dim has the expected channels and gives
+    // output_dim, which is fixed when the layer is built
+    pub fn accepts(&self, input_dim: (usize, usize, usize)) -> bool {
+        let extent = |dilation: usize| (self.kernel_size - 1) * dilation + 1;
+        input_dim.2 == self.input_dim.2
+            && input_dim.0 + 2 * self.padding.0 >= extent(self.dilatation_rate.0)
+            && input_dim.1 + 2 * self.padding.1 >= extent(self.dilatation_rate.1)
+            && get_output_dim(
+                input_dim,
+                self.padding,
+                self.kernel_size,
+                self.dilatation_rate,
+                self.strides,
+                self.filters,
+            ) == self.output_dim
+    }
+
     pub fn activation_function(&self) -> ActivationFunctionType {
         self.activation_function
     }
 
+    pub fn kernels(&self) -> &[Array<f32, Ix3>] {
+        &self.kernels
+    }
+
+    pub fn kernels_gradient(&self) -> &[Array<f32, Ix3>] {
+        &self.kernels_gradient
+    }
+
     pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
         let input_padded = add_padding(input, &self.padding);
-        let mut output = Array::zeros((self.output_dim.0, self.output_dim.1, self.output_dim.2));
+        let preactivation = self.convolve(&input_padded);
+        Ok(self.activate(&preactivation))
+    }
+
+    // Same as forward, but keeps what backward needs for every sample of the
+    // batch. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let input_padded = add_padding(input, &self.padding);
+            let preactivation = self.convolve(&input_padded);
+            let output = self.activate(&preactivation);
+            outputs.push(output.clone());
+            self.cache.push(Conv2dCache {
+                input_padded,
+                preactivation,
+                output,
+            });
+        }
+        Ok(outputs)
+    }
+
+    // Accumulates the gradient of every kernel over the cached batch and
+    // returns the gradient of each (unpadded) input
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(Conv2dError::MissingForwardError));
+        }
 
-        let input_padded_channel_size = input_padded.shape()[2];
+        // forward leaves softmax outputs untouched, so its gradient does too
+        let activation_function = match self.activation_function {
+            ActivationFunctionType::Softmax => ActivationFunctionType::None,
+            activation_function => activation_function,
+        };
+
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            if grad_output.shape() != cache.output.shape() {
+                return Err(Box::new(Conv2dError::GradientShapeError));
+            }
+            let grad_preactivation = activation_backward(
+                activation_function,
+                &cache.preactivation,
+                &cache.output,
+                grad_output,
+            );
+
+            let batch_kernels_gradient: Vec<Array<f32, Ix3>> = (0..self.filters)
+                .into_par_iter()
+                .map(|feature| {
+                    let mut kernel_gradient = Array::zeros(self.kernels[feature].raw_dim());
+                    for ((row, col), grad) in grad_preactivation
+                        .index_axis(Axis(2), feature)
+                        .indexed_iter()
+                    {
+                        let input_slice = cache.input_padded.slice(self.window(row, col, feature));
+                        kernel_gradient.scaled_add(*grad, &input_slice);
+                    }
+                    kernel_gradient
+                })
+                .collect();
+            for (kernel_gradient, batch_kernel_gradient) in self
+                .kernels_gradient
+                .iter_mut()
+                .zip(&batch_kernels_gradient)
+            {
+                *kernel_gradient += batch_kernel_gradient;
+            }
+
+            let mut grad_input_padded = Array::zeros(cache.input_padded.raw_dim());
+            for ((row, col, feature), grad) in grad_preactivation.indexed_iter() {
+                grad_input_padded
+                    .slice_mut(self.window(row, col, feature))
+                    .scaled_add(*grad, &self.kernels[feature]);
+            }
+            grad_inputs.push(remove_padding(&grad_input_padded, &self.padding));
+        }
+
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        for kernel_gradient in &mut self.kernels_gradient {
+            kernel_gradient.fill(0.0);
+        }
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.kernels.iter().collect()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.kernels
+            .iter_mut()
+            .zip(&self.kernels_gradient)
+            .map(|(kernel, kernel_gradient)| Parameter {
+                value: kernel,
+                gradient: kernel_gradient,
+            })
+            .collect()
+    }
+
+    // Input cells read by the output cell (row, col, feature), taking strides,
+    // dilation and the channels of the feature group into account
+    fn window(
+        &self,
+        row: usize,
+        col: usize,
+        feature: usize,
+    ) -> SliceInfo<[SliceInfoElem; 3], Ix3, Ix3> {
+        let first_row = row * self.strides.0;
+        let first_col = col * self.strides.1;
+        let last_row = first_row + (self.kernel_size - 1) * self.dilatation_rate.0;
+        let last_col = first_col + (self.kernel_size - 1) * self.dilatation_rate.1;
+        let group_channels = self.input_dim.2 / self.groups;
+        let group = feature / (self.filters / self.groups);
+        s![
+            first_row..=last_row;self.dilatation_rate.0,
+            first_col..=last_col;self.dilatation_rate.1,
+            group * group_channels..(group + 1) * group_channels
+        ]
+    }
+
+    fn convolve(&self, input_padded: &Array<f32, Ix3>) -> Array<f32, Ix3> {
+        let mut output = Array::zeros((self.output_dim.0, self.output_dim.1, self.output_dim.2));
 
-        // TODO: Dilation and stride are not being considered now
         Zip::indexed(output.view_mut()).par_for_each(|(row, col, feature), value| {
-            let max_row = row + self.kernel_size;
-            let max_col = col + self.kernel_size;
             let kernel = &self.kernels[feature];
-
-            let input_slice =
-                input_padded.slice(s!(row..max_row, col..max_col, 0..input_padded_channel_size));
+            let input_slice = input_padded.slice(self.window(row, col, feature));
 
             /*let output_cel = Zip::from(kernel).and(&input_slice).par_fold(
                 || 0.,
                 |sum, kernel_elem, input_elem| sum + kernel_elem * input_elem,
                 |sum, other_sum| sum + other_sum
             );*/
-            let output_cel = kernel.mul(&input_slice).sum();
-
-            *value = match self.activation_function {
-                ActivationFunctionType::Relu => relu(&output_cel),
-                ActivationFunctionType::Sigmoid => sigmoid(&output_cel),
-                ActivationFunctionType::LeakyRelu => leaky_relu(&output_cel, None),
-                ActivationFunctionType::Tanh => tanh(&output_cel),
-                // TODO: enable softmax for Conv2D
-                // ActivationFunctionType::Softmax => softmax(&output_celt),
-                _ => output_cel,
-            };
+            *value = kernel.mul(&input_slice).sum();
         });
 
-        Ok(output)
+        output
+    }
+
+    fn activate(&self, preactivation: &Array<f32, Ix3>) -> Array<f32, Ix3> {
+        preactivation.map(|output_cel| match self.activation_function {
+            ActivationFunctionType::Relu => relu(output_cel),
+            ActivationFunctionType::Sigmoid => sigmoid(output_cel),
+            ActivationFunctionType::LeakyRelu => leaky_relu(output_cel, None),
+            ActivationFunctionType::Tanh => tanh(output_cel),
+            // TODO: enable softmax for Conv2D
+            // ActivationFunctionType::Softmax => softmax(&output_celt),
+            _ => *output_cel,
+        })
     }
 }
 
@@ -159,4 +347,10 @@ pub fn get_output_dim(
 pub enum Conv2dError {
     #[error("invalid kernel size")]
     KernelSizeError,
+    #[error("channels and filters should be multiples of groups")]
+    GroupsError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
 }
@@ -0,0 +1,287 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Axis, Ix2, Ix3, SliceInfo, SliceInfoElem};
+use ndarray_rand::RandomExt;
+use rand::distributions::Uniform;
+
+use crate::{
+    activation::{
+        activate, activation_backward, softmax, softmax_backward, ActivationFunctionType,
+    },
+    layer::Parameter,
+};
+
+// Transposed convolution over (height, width, channels) inputs: every input
+// cell scatters its kernel-weighted values into a (kernel_size, kernel_size)
+// window of the output, windows being `strides` apart. Padding crops the
+// borders of the result, so with strides s, kernel k and padding p an input
+// of height h gives (h - 1) * s + k - 2 * p rows.
+pub struct Conv2dTransposeLayer {
+    pub filters: usize,
+    pub kernel_size: usize,
+    pub channels: usize,
+    pub strides: (usize, usize),
+    pub padding: (usize, usize),
+    activation_function: ActivationFunctionType,
+    // (channels, kernel_size * kernel_size, filters)
+    kernels: Array<f32, Ix3>,
+    kernels_gradient: Array<f32, Ix3>,
+    cache: Vec<Conv2dTransposeCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct Conv2dTransposeCache {
+    input: Array<f32, Ix2>,
+    input_dim: (usize, usize, usize),
+    preactivation: Array<f32, Ix3>,
+    output: Array<f32, Ix3>,
+}
+
+impl Conv2dTransposeLayer {
+    pub fn new(
+        filters: usize,
+        kernel_size: usize,
+        channels: usize,
+        strides: Option<(usize, usize)>,
+        padding: Option<(usize, usize)>,
+        activation_function_type: Option<ActivationFunctionType>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if kernel_size < 1 {
+            return Err(Box::new(Conv2dTransposeError::KernelSizeError));
+        }
+        let strides = strides.unwrap_or((1, 1));
+        let padding = padding.unwrap_or((0, 0));
+        if strides.0 < 1
+            || strides.1 < 1
+            || 2 * padding.0 >= kernel_size
+            || 2 * padding.1 >= kernel_size
+        {
+            return Err(Box::new(Conv2dTransposeError::PaddingError));
+        }
+        let kernels_dim = (channels, kernel_size * kernel_size, filters);
+
+        Ok(Self {
+            filters,
+            kernel_size,
+            channels,
+            strides,
+            padding,
+            activation_function: activation_function_type.unwrap_or(ActivationFunctionType::None),
+            kernels: Array::random(kernels_dim, Uniform::new(-1.0, 1.0)),
+            kernels_gradient: Array::zeros(kernels_dim),
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let (input_height, input_width, _) = input_dim;
+        let (full_height, full_width) = self.full_dim(input_height, input_width);
+        (
+            full_height.saturating_sub(2 * self.padding.0),
+            full_width.saturating_sub(2 * self.padding.1),
+            self.filters,
+        )
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.activation_function
+    }
+
+    pub fn kernels(&self) -> &Array<f32, Ix3> {
+        &self.kernels
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let cells = self.cells(input)?;
+        let preactivation = self.scatter(&cells, input.dim())?;
+        Ok(self.activate(&preactivation))
+    }
+
+    // Same as forward, but keeps what backward needs for every sample of the
+    // batch. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let cells = self.cells(input)?;
+            let preactivation = self.scatter(&cells, input.dim())?;
+            let output = self.activate(&preactivation);
+            outputs.push(output.clone());
+            self.cache.push(Conv2dTransposeCache {
+                input: cells,
+                input_dim: input.dim(),
+                preactivation,
+                output,
+            });
+        }
+        Ok(outputs)
+    }
+
+    // Gathers back the output window of every input cell, which makes the
+    // input gradient a regular convolution of the output gradient
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(Conv2dTransposeError::MissingForwardError));
+        }
+        let patch_size = self.kernel_size * self.kernel_size * self.filters;
+        let kernels = self.kernels.to_shape((self.channels, patch_size))?;
+
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            if grad_output.shape() != cache.output.shape() {
+                return Err(Box::new(Conv2dTransposeError::GradientShapeError));
+            }
+            let grad_preactivation =
+                self.activation_backward(&cache.preactivation, &cache.output, grad_output);
+            let (input_height, input_width, _) = cache.input_dim;
+            let (full_height, full_width) = self.full_dim(input_height, input_width);
+            let mut grad_full = Array::zeros((full_height, full_width, self.filters));
+            grad_full
+                .slice_mut(s![
+                    self.padding.0..full_height - self.padding.0,
+                    self.padding.1..full_width - self.padding.1,
+                    ..
+                ])
+                .assign(&grad_preactivation);
+
+            let mut grad_patches = Array::zeros((input_height * input_width, patch_size));
+            for (cell, mut grad_patch) in grad_patches.outer_iter_mut().enumerate() {
+                let window = grad_full.slice(self.window(cell / input_width, cell % input_width));
+                grad_patch.assign(&window.to_shape(patch_size)?);
+            }
+
+            let batch_kernels_gradient = cache.input.t().dot(&grad_patches);
+            self.kernels_gradient += &batch_kernels_gradient.to_shape(self.kernels.raw_dim())?;
+            let grad_input = grad_patches.dot(&kernels.t());
+            grad_inputs.push(grad_input.into_shape(cache.input_dim)?);
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.kernels_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.kernels]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![Parameter {
+            value: &mut self.kernels,
+            gradient: &self.kernels_gradient,
+        }]
+    }
+
+    // Softmax gives every output cell its own distribution over the filters
+    fn activate(&self, preactivation: &Array<f32, Ix3>) -> Array<f32, Ix3> {
+        if self.activation_function != ActivationFunctionType::Softmax {
+            return activate(self.activation_function, preactivation);
+        }
+        let mut output = preactivation.clone();
+        for mut cell in output.exact_chunks_mut((1, 1, self.filters)) {
+            let cell_output = softmax(&cell.to_owned());
+            cell.assign(&cell_output);
+        }
+        output
+    }
+
+    fn activation_backward(
+        &self,
+        preactivation: &Array<f32, Ix3>,
+        output: &Array<f32, Ix3>,
+        grad_output: &Array<f32, Ix3>,
+    ) -> Array<f32, Ix3> {
+        if self.activation_function != ActivationFunctionType::Softmax {
+            return activation_backward(
+                self.activation_function,
+                preactivation,
+                output,
+                grad_output,
+            );
+        }
+        let mut grad_preactivation = grad_output.clone();
+        for (mut cell, cell_output) in grad_preactivation
+            .exact_chunks_mut((1, 1, self.filters))
+            .into_iter()
+            .zip(output.exact_chunks((1, 1, self.filters)))
+        {
+            let cell_grad = softmax_backward(&cell_output.to_owned(), &cell.to_owned());
+            cell.assign(&cell_grad);
+        }
+        grad_preactivation
+    }
+
+    // Output size before cropping the padding
+    fn full_dim(&self, input_height: usize, input_width: usize) -> (usize, usize) {
+        (
+            input_height.saturating_sub(1) * self.strides.0 + self.kernel_size,
+            input_width.saturating_sub(1) * self.strides.1 + self.kernel_size,
+        )
+    }
+
+    // Uncropped output cells written by the input cell (row, col)
+    fn window(&self, row: usize, col: usize) -> SliceInfo<[SliceInfoElem; 3], Ix3, Ix3> {
+        let first_row = row * self.strides.0;
+        let first_col = col * self.strides.1;
+        s![
+            first_row..first_row + self.kernel_size,
+            first_col..first_col + self.kernel_size,
+            ..
+        ]
+    }
+
+    // One row of channels per input cell
+    fn cells(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix2>, Box<dyn Error>> {
+        let (height, width, channels) = input.dim();
+        if channels != self.channels {
+            return Err(Box::new(Conv2dTransposeError::InvalidDimensionsError));
+        }
+        Ok(input.to_shape((height * width, channels))?.to_owned())
+    }
+
+    fn scatter(
+        &self,
+        cells: &Array<f32, Ix2>,
+        input_dim: (usize, usize, usize),
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (input_height, input_width, _) = input_dim;
+        let patch_size = self.kernel_size * self.kernel_size * self.filters;
+        let patches = cells.dot(&self.kernels.to_shape((self.channels, patch_size))?);
+
+        let (full_height, full_width) = self.full_dim(input_height, input_width);
+        let mut output = Array::zeros((full_height, full_width, self.filters));
+        for (cell, patch) in patches.axis_iter(Axis(0)).enumerate() {
+            let patch = patch.to_shape((self.kernel_size, self.kernel_size, self.filters))?;
+            let mut window = output.slice_mut(self.window(cell / input_width, cell % input_width));
+            window += &patch;
+        }
+        Ok(output
+            .slice(s![
+                self.padding.0..full_height - self.padding.0,
+                self.padding.1..full_width - self.padding.1,
+                ..
+            ])
+            .to_owned())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Conv2dTransposeError {
+    #[error("invalid kernel size")]
+    KernelSizeError,
+    #[error("strides should be at least 1 and padding should crop less than a kernel")]
+    PaddingError,
+    #[error("input channels should match the layer channels")]
+    InvalidDimensionsError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -0,0 +1,68 @@
+// user-defined layers
+//
+// Layers living outside of this crate implement CustomLayer and are pushed
+// into a model as Layer::Custom(Box::new(layer)). They follow the contract of
+// the built-in layers: forward_train replaces the cache used by backward,
+// backward accumulates the parameter gradients until zero_gradients is called
+// and returns the gradient of every input, and parameters and parameters_mut
+// always list the same tensors in the same order.
+
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{Mode, Parameter},
+};
+
+pub trait CustomLayer {
+    // Shown by SequentialModel::summary
+    fn type_name(&self) -> &'static str {
+        "Custom"
+    }
+
+    fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    // Shape of the output for an input of shape input_dim, None when the
+    // layer cannot take such an input
+    fn output_dim(&self, input_dim: (usize, usize, usize)) -> Option<(usize, usize, usize)>;
+
+    fn forward(
+        &self,
+        input: &Array<f32, Ix3>,
+        mode: Mode,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>>;
+
+    fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>>;
+
+    fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>>;
+
+    fn zero_gradients(&mut self) {}
+
+    fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        Vec::new()
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        Vec::new()
+    }
+
+    // State saved along with the parameters but not trained by gradients
+    fn buffers(&self) -> Vec<&Array<f32, Ix3>> {
+        Vec::new()
+    }
+
+    // Layers with buffers should override it along with buffers
+    fn parameters_and_buffers_mut(&mut self) -> (Vec<Parameter<'_>>, Vec<&mut Array<f32, Ix3>>) {
+        (self.parameters_mut(), Vec::new())
+    }
+}
@@ -1,10 +1,13 @@
 use std::error::Error;
 
-use ndarray::{Array, Axis, Ix3};
+use ndarray::{Array, Axis, Ix2, Ix3};
 use ndarray_rand::RandomExt;
-use rand::distributions::Uniform;
+use rand::{distributions::Uniform, Rng};
 
-use crate::activation::{leaky_relu, relu, sigmoid, softmax, tanh, ActivationFunctionType};
+use crate::{
+    activation::{activate, activation_backward, ActivationFunctionType},
+    layer::Parameter,
+};
 //use rayon::iter::ParallelIterator;
 
 pub struct DenseLayer {
@@ -13,6 +16,16 @@ pub struct DenseLayer {
     pub weights: Array<f32, Ix3>,
     pub bias: Array<f32, Ix3>,
     pub activation_function: ActivationFunctionType,
+    pub weights_gradient: Array<f32, Ix3>,
+    pub bias_gradient: Array<f32, Ix3>,
+    cache: Vec<DenseCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct DenseCache {
+    input: Array<f32, Ix3>,
+    preactivation: Array<f32, Ix3>,
+    output: Array<f32, Ix3>,
 }
 
 /*
@@ -25,8 +38,24 @@ impl DenseLayer {
         output_size: usize,
         activation_function: Option<ActivationFunctionType>,
     ) -> Self {
-        let layers = Array::random((input_size, output_size, 1), Uniform::new(-1.0, 1.0));
-        let bias = Array::random((1, output_size, 1), Uniform::new(-10.0, 10.0));
+        Self::new_using(
+            input_size,
+            output_size,
+            activation_function,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    // Same as new, drawing the weights and bias from rng
+    pub fn new_using<R: Rng + ?Sized>(
+        input_size: usize,
+        output_size: usize,
+        activation_function: Option<ActivationFunctionType>,
+        rng: &mut R,
+    ) -> Self {
+        let layers =
+            Array::random_using((input_size, output_size, 1), Uniform::new(-1.0, 1.0), rng);
+        let bias = Array::random_using((1, output_size, 1), Uniform::new(-10.0, 10.0), rng);
 
         DenseLayer {
             input_size,
@@ -34,6 +63,9 @@ impl DenseLayer {
             weights: layers,
             bias,
             activation_function: activation_function.unwrap_or(ActivationFunctionType::None),
+            weights_gradient: Array::zeros((input_size, output_size, 1)),
+            bias_gradient: Array::zeros((1, output_size, 1)),
+            cache: Vec::new(),
         }
     }
 }
@@ -44,24 +76,104 @@ impl DenseLayer {
     }
 
     pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let partial_result = self.preactivation(input)?;
+        Ok(self.activate(&partial_result))
+    }
+
+    // Same as forward, but keeps what backward needs for every sample of the
+    // batch. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let preactivation = self.preactivation(input)?;
+            let output = self.activate(&preactivation);
+            outputs.push(output.clone());
+            self.cache.push(DenseCache {
+                input: input.clone(),
+                preactivation,
+                output,
+            });
+        }
+        Ok(outputs)
+    }
+
+    // Accumulates the weights and bias gradients of the cached batch and
+    // returns the gradient of each input
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(DenseError::MissingForwardError));
+        }
+
+        let weights = self.weights.index_axis(Axis(2), 0);
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let grad_preactivation = activation_backward(
+                self.activation_function,
+                &cache.preactivation,
+                &cache.output,
+                &grad_output.to_shape((1, self.output_size, 1))?.to_owned(),
+            );
+            let grad_preactivation_2d = grad_preactivation.index_axis(Axis(2), 0);
+            let input_2d = cache
+                .input
+                .to_shape((1, self.input_size))?
+                .into_dimensionality::<Ix2>()?;
+
+            self.weights_gradient
+                .index_axis_mut(Axis(2), 0)
+                .scaled_add(1.0, &input_2d.t().dot(&grad_preactivation_2d));
+            self.bias_gradient += &grad_preactivation;
+
+            let grad_input = grad_preactivation_2d.dot(&weights.t());
+            grad_inputs.push(grad_input.to_shape(cache.input.raw_dim())?.to_owned());
+        }
+
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.weights_gradient.fill(0.0);
+        self.bias_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.weights, &self.bias]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter {
+                value: &mut self.weights,
+                gradient: &self.weights_gradient,
+            },
+            Parameter {
+                value: &mut self.bias,
+                gradient: &self.bias_gradient,
+            },
+        ]
+    }
+
+    fn preactivation(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
         if input.len_of(Axis(0)) != 1 && input.len_of(Axis(2)) != 1 {
             return Err(Box::new(DenseError::InvalidDimensionsError));
         }
-        let partial_result = &input
+        Ok(&input
             .index_axis(Axis(2), 0)
             .dot(&self.weights.index_axis(Axis(2), 0))
             .to_shape((1, self.output_size, 1))?
-            + &self.bias;
-
-        let result = match self.activation_function {
-            ActivationFunctionType::Relu => partial_result.map(relu),
-            ActivationFunctionType::Sigmoid => partial_result.map(sigmoid),
-            ActivationFunctionType::LeakyRelu => partial_result.map(|x| leaky_relu(x, None)),
-            ActivationFunctionType::Tanh => partial_result.map(tanh),
-            ActivationFunctionType::Softmax => softmax(&partial_result),
-            ActivationFunctionType::None => partial_result.clone(),
-        };
-        Ok(result)
+            + &self.bias)
+    }
+
+    fn activate(&self, partial_result: &Array<f32, Ix3>) -> Array<f32, Ix3> {
+        activate(self.activation_function, partial_result)
     }
 }
 
@@ -69,4 +181,6 @@ impl DenseLayer {
 pub enum DenseError {
     #[error("Dimensions should match for forwarding")]
     InvalidDimensionsError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
 }
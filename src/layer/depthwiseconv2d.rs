@@ -0,0 +1,90 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{conv2d::Conv2dLayer, Parameter},
+};
+
+// Convolves every channel on its own with `depth_multiplier` kernels, a
+// Conv2dLayer with as many groups as channels
+pub struct DepthwiseConv2dLayer {
+    pub depth_multiplier: usize,
+    conv: Conv2dLayer,
+}
+
+impl DepthwiseConv2dLayer {
+    pub fn new(
+        kernel_size: usize,
+        input_dim: (usize, usize, usize),
+        depth_multiplier: Option<usize>,
+        padding: Option<(usize, usize)>,
+        strides: Option<(usize, usize)>,
+        dilation_rate: Option<(usize, usize)>,
+        activation_function_type: Option<ActivationFunctionType>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let depth_multiplier = depth_multiplier.unwrap_or(1);
+        let channels = input_dim.2;
+        let conv = Conv2dLayer::new(
+            channels * depth_multiplier,
+            kernel_size,
+            input_dim,
+            padding,
+            strides,
+            dilation_rate,
+            activation_function_type,
+        )?
+        .with_groups(channels)?;
+        Ok(Self {
+            depth_multiplier,
+            conv,
+        })
+    }
+
+    pub fn output_dim(&self) -> (usize, usize, usize) {
+        self.conv.output_dim
+    }
+
+    pub fn accepts(&self, input_dim: (usize, usize, usize)) -> bool {
+        self.conv.accepts(input_dim)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.conv.activation_function()
+    }
+
+    pub fn kernels(&self) -> &[Array<f32, Ix3>] {
+        self.conv.kernels()
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.conv.forward(input)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.conv.forward_train(inputs)
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.conv.backward(grad_outputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.conv.zero_gradients();
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.conv.parameters()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.conv.parameters_mut()
+    }
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+use ndarray_rand::RandomExt;
+use rand::distributions::Bernoulli;
+
+use crate::{activation::ActivationFunctionType, layer::Mode};
+
+// Zeroes each activation with probability rate during training and scales the
+// kept ones by 1 / (1 - rate), so that inference is the identity
+pub struct DropoutLayer {
+    pub rate: f32,
+    masks: Vec<Array<f32, Ix3>>,
+}
+
+impl DropoutLayer {
+    pub fn new(rate: f32) -> Result<Self, Box<dyn Error>> {
+        if !(0.0..1.0).contains(&rate) {
+            return Err(Box::new(DropoutError::RateError));
+        }
+        Ok(Self {
+            rate,
+            masks: Vec::new(),
+        })
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(
+        &self,
+        input: &Array<f32, Ix3>,
+        mode: Mode,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        match mode {
+            Mode::Training => Ok(input * &self.random_mask(input)?),
+            Mode::Inference => Ok(input.clone()),
+        }
+    }
+
+    // Keeps the mask of every sample of the batch. The cache is replaced on
+    // each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.masks = inputs
+            .iter()
+            .map(|input| self.random_mask(input))
+            .collect::<Result<_, _>>()?;
+        Ok(inputs
+            .iter()
+            .zip(&self.masks)
+            .map(|(input, mask)| input * mask)
+            .collect())
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.masks.len() {
+            return Err(Box::new(DropoutError::MissingForwardError));
+        }
+        Ok(grad_outputs
+            .iter()
+            .zip(&self.masks)
+            .map(|(grad_output, mask)| grad_output * mask)
+            .collect())
+    }
+
+    fn random_mask(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let keep_probability = 1.0 - self.rate;
+        let keep = Bernoulli::new(f64::from(keep_probability))?;
+        Ok(Array::random(input.raw_dim(), keep).map(|kept| {
+            if *kept {
+                1.0 / keep_probability
+            } else {
+                0.0
+            }
+        }))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DropoutError {
+    #[error("dropout rate should be in [0, 1)")]
+    RateError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
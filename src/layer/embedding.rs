@@ -0,0 +1,156 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Ix3};
+use ndarray_rand::RandomExt;
+use rand::distributions::Uniform;
+
+use crate::{activation::ActivationFunctionType, layer::Parameter};
+
+// Maps integer token ids to learnable vectors. Ids come as whole f32 values
+// in any (a, b, c) layout, the same way sparse targets hold class indices,
+// and are read in order into a (tokens, 1, embedding_size) sequence. The
+// padding id always maps to zeros and its vector is never trained. Ids are
+// not differentiable, so backward returns zero input gradients.
+pub struct EmbeddingLayer {
+    pub vocabulary_size: usize,
+    pub embedding_size: usize,
+    pub padding_index: Option<usize>,
+    pub embeddings: Array<f32, Ix3>,
+    pub embeddings_gradient: Array<f32, Ix3>,
+    cache: Vec<EmbeddingCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct EmbeddingCache {
+    input_dim: (usize, usize, usize),
+    tokens: Vec<usize>,
+}
+
+impl EmbeddingLayer {
+    pub fn new(
+        vocabulary_size: usize,
+        embedding_size: usize,
+        padding_index: Option<usize>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if padding_index.is_some_and(|index| index >= vocabulary_size) {
+            return Err(Box::new(EmbeddingError::InvalidTokenError));
+        }
+        let mut embeddings = Array::random(
+            (vocabulary_size, embedding_size, 1),
+            Uniform::new(-0.05, 0.05),
+        );
+        if let Some(index) = padding_index {
+            embeddings.slice_mut(s![index, .., ..]).fill(0.0);
+        }
+        Ok(Self {
+            vocabulary_size,
+            embedding_size,
+            padding_index,
+            embeddings_gradient: Array::zeros(embeddings.raw_dim()),
+            embeddings,
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let (a, b, c) = input_dim;
+        (a * b * c, 1, self.embedding_size)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let tokens = self.tokens(input)?;
+        let mut output = Array::zeros((tokens.len(), 1, self.embedding_size));
+        for (mut row, &token) in output.outer_iter_mut().zip(&tokens) {
+            if Some(token) != self.padding_index {
+                row.assign(&self.embeddings.slice(s![token, .., ..]).t());
+            }
+        }
+        Ok(output)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| {
+                Ok(EmbeddingCache {
+                    input_dim: input.dim(),
+                    tokens: self.tokens(input)?,
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    // Adds the gradient of every step to the vector of its token
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(EmbeddingError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let tokens = &cache.tokens;
+            if grad_output.dim() != (tokens.len(), 1, self.embedding_size) {
+                return Err(Box::new(EmbeddingError::GradientShapeError));
+            }
+            for (grad, &token) in grad_output.outer_iter().zip(tokens) {
+                if Some(token) != self.padding_index {
+                    let mut gradient = self.embeddings_gradient.slice_mut(s![token, .., ..]);
+                    gradient += &grad.t();
+                }
+            }
+            grad_inputs.push(Array::zeros(cache.input_dim));
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.embeddings_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.embeddings]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![Parameter {
+            value: &mut self.embeddings,
+            gradient: &self.embeddings_gradient,
+        }]
+    }
+
+    #[expect(clippy::cast_possible_truncation)]
+    #[expect(clippy::cast_sign_loss)]
+    #[expect(clippy::cast_precision_loss)]
+    fn tokens(&self, input: &Array<f32, Ix3>) -> Result<Vec<usize>, Box<dyn Error>> {
+        input
+            .iter()
+            .map(|&id| {
+                if id.fract() != 0.0 || id < 0.0 || id >= self.vocabulary_size as f32 {
+                    Err(Box::new(EmbeddingError::InvalidTokenError).into())
+                } else {
+                    Ok(id as usize)
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbeddingError {
+    #[error("token ids should be whole numbers below the vocabulary size")]
+    InvalidTokenError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
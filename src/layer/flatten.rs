@@ -5,11 +5,13 @@ use ndarray::{Array, Ix3};
 use crate::activation::ActivationFunctionType;
 
 #[derive(Debug, Default)]
-pub struct FlattenLayer;
+pub struct FlattenLayer {
+    input_dims: Vec<Ix3>,
+}
 
 impl FlattenLayer {
     pub fn new() -> Self {
-        Self
+        Self::default()
     }
 }
 
@@ -26,4 +28,38 @@ impl FlattenLayer {
             .unwrap()
             .to_owned())
     }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.input_dims = inputs.iter().map(Array::raw_dim).collect();
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    // Reshapes each gradient back to the shape its input had in forward_train
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.input_dims.len() {
+            return Err(Box::new(FlattenError::MissingForwardError));
+        }
+        grad_outputs
+            .iter()
+            .zip(&self.input_dims)
+            .map(|(grad_output, input_dim)| {
+                Ok(Array::from_shape_vec(
+                    *input_dim,
+                    grad_output.iter().copied().collect(),
+                )?)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FlattenError {
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
 }
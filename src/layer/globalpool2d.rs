@@ -0,0 +1,163 @@
+use std::error::Error;
+
+use ndarray::{Array, Axis, Ix3};
+use ndarray_stats::QuantileExt;
+
+use crate::activation::ActivationFunctionType;
+
+// Global pooling reduces every channel of a (height, width, channels) input
+// to a single value. The output is a (1, channels, 1) vector, the layout
+// DenseLayer expects, so no FlattenLayer is needed before a classifier.
+
+#[derive(Debug, Default)]
+pub struct GlobalAveragePooling2dLayer {
+    input_dims: Vec<(usize, usize, usize)>,
+}
+
+impl GlobalAveragePooling2dLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        (1, input_dim.2, 1)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (height, width, channels) = input.dim();
+        let means = input
+            .to_shape((height * width, channels))?
+            .mean_axis(Axis(0))
+            .ok_or(GlobalPooling2dError::EmptyInputError)?;
+        Ok(means.into_shape((1, channels, 1))?)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.input_dims = inputs.iter().map(Array::dim).collect();
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.input_dims.len() {
+            return Err(Box::new(GlobalPooling2dError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (&input_dim, grad_output) in self.input_dims.iter().zip(grad_outputs) {
+            let (height, width, channels) = input_dim;
+            if grad_output.dim() != self.output_dim(input_dim) {
+                return Err(Box::new(GlobalPooling2dError::GradientShapeError));
+            }
+            // every position shares the channel gradient equally
+            let grad = &grad_output.to_shape((1, 1, channels))? / (height * width) as f32;
+            grad_inputs.push(Array::zeros(input_dim) + &grad);
+        }
+        Ok(grad_inputs)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GlobalMaxPooling2dLayer {
+    cache: Vec<GlobalMaxPooling2dCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+#[derive(Debug)]
+struct GlobalMaxPooling2dCache {
+    input_dim: (usize, usize, usize),
+    argmax: Vec<usize>,
+}
+
+impl GlobalMaxPooling2dLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        (1, input_dim.2, 1)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (output, _) = Self::pool(input)?;
+        Ok(output)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let (output, argmax) = Self::pool(input)?;
+            outputs.push(output);
+            self.cache.push(GlobalMaxPooling2dCache {
+                input_dim: input.dim(),
+                argmax,
+            });
+        }
+        Ok(outputs)
+    }
+
+    // Routes each channel gradient to the position that was its maximum
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(GlobalPooling2dError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            if grad_output.dim() != self.output_dim(cache.input_dim) {
+                return Err(Box::new(GlobalPooling2dError::GradientShapeError));
+            }
+            let (_, width, _) = cache.input_dim;
+            let mut grad_input = Array::zeros(cache.input_dim);
+            for (channel, (&position, grad)) in cache.argmax.iter().zip(grad_output).enumerate() {
+                grad_input[[position / width, position % width, channel]] = *grad;
+            }
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+
+    // Max of every channel along with its flattened (row, col) position
+    #[expect(clippy::type_complexity)]
+    fn pool(input: &Array<f32, Ix3>) -> Result<(Array<f32, Ix3>, Vec<usize>), Box<dyn Error>> {
+        let (height, width, channels) = input.dim();
+        let positions = input.to_shape((height * width, channels))?;
+        let mut output = Array::zeros((1, channels, 1));
+        let mut argmax = Vec::with_capacity(channels);
+        for (channel, values) in positions.axis_iter(Axis(1)).enumerate() {
+            let position = values.argmax()?;
+            output[[0, channel, 0]] = values[position];
+            argmax.push(position);
+        }
+        Ok((output, argmax))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GlobalPooling2dError {
+    #[error("input should have at least one position")]
+    EmptyInputError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
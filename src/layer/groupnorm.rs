@@ -0,0 +1,143 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord},
+    layer::{layernorm::NormalizationError, Parameter},
+};
+
+// Splits the channels of a (height, width, channels) sample into groups and
+// normalizes each group with its own mean and variance. With one channel per
+// group it is instance normalization. Backward is derived by the autograd
+// tape.
+pub struct GroupNormLayer {
+    pub groups: usize,
+    pub channels: usize,
+    pub epsilon: f32,
+    pub gamma: Array<f32, Ix3>,
+    pub beta: Array<f32, Ix3>,
+    pub gamma_gradient: Array<f32, Ix3>,
+    pub beta_gradient: Array<f32, Ix3>,
+    cache: Vec<TapeRecord>,
+}
+
+impl GroupNormLayer {
+    pub fn new(
+        groups: usize,
+        channels: usize,
+        epsilon: Option<f32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if groups == 0 || !channels.is_multiple_of(groups) {
+            return Err(Box::new(NormalizationError::GroupsError));
+        }
+        Ok(Self {
+            groups,
+            channels,
+            epsilon: epsilon.unwrap_or(1e-3),
+            gamma: Array::ones((1, 1, channels)),
+            beta: Array::zeros((1, 1, channels)),
+            gamma_gradient: Array::zeros((1, 1, channels)),
+            beta_gradient: Array::zeros((1, 1, channels)),
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn instance_norm(channels: usize, epsilon: Option<f32>) -> Result<Self, Box<dyn Error>> {
+        Self::new(channels, channels, epsilon)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input)?.output()
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| self.record(input))
+            .collect::<Result<_, _>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(NormalizationError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (record, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let (grad_input, grad_parameters) = record.backward(grad_output)?;
+            self.gamma_gradient += &grad_parameters[0];
+            self.beta_gradient += &grad_parameters[1];
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.gamma_gradient.fill(0.0);
+        self.beta_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter {
+                value: &mut self.gamma,
+                gradient: &self.gamma_gradient,
+            },
+            Parameter {
+                value: &mut self.beta,
+                gradient: &self.beta_gradient,
+            },
+        ]
+    }
+
+    fn record(&self, input: &Array<f32, Ix3>) -> Result<TapeRecord, Box<dyn Error>> {
+        let (height, width, channels) = input.dim();
+        if channels != self.channels {
+            return Err(Box::new(NormalizationError::InvalidDimensionsError));
+        }
+        let group_size = channels / self.groups;
+
+        let mut tape = Tape::new();
+        let x = tape.variable(input.clone().into_dyn());
+        let gamma = tape.variable(self.gamma.clone().into_dyn());
+        let beta = tape.variable(self.beta.clone().into_dyn());
+
+        // (positions, groups, channels of the group), statistics over 0 and 2
+        let grouped = tape.reshape(x, &[height * width, self.groups, group_size])?;
+        let position_mean = tape.mean_axis(grouped, 0)?;
+        let mean = tape.mean_axis(position_mean, 2)?;
+        let centered = tape.sub(grouped, mean)?;
+        let squared = tape.powi(centered, 2);
+        let position_variance = tape.mean_axis(squared, 0)?;
+        let variance = tape.mean_axis(position_variance, 2)?;
+        let shifted_variance = tape.add_scalar(variance, self.epsilon);
+        let std = tape.sqrt(shifted_variance);
+        let normalized = tape.div(centered, std)?;
+        let ungrouped = tape.reshape(normalized, &[height, width, channels])?;
+        let scaled = tape.mul(ungrouped, gamma)?;
+        let output = tape.add(scaled, beta)?;
+
+        Ok(TapeRecord {
+            tape,
+            input: x,
+            parameters: vec![gamma, beta],
+            output,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord, Var},
+    layer::{
+        recurrent::{
+            check_states, final_states_of, projected_with_bias, record_projection, record_states,
+            recurrent_backward, sequence_output, tape_activation, RecurrentWeights,
+        },
+        Parameter,
+    },
+};
+
+// Gated recurrent unit over (length, 1, features) sequences, with the
+// update, reset and candidate blocks stored in that order. The reset gate is
+// applied to the state before its product with the recurrent weights, and
+// the new state is z * h + (1 - z) * candidate. Backward is derived by the
+// autograd tape.
+pub struct GruLayer {
+    pub input_size: usize,
+    pub units: usize,
+    pub return_sequences: bool,
+    activation_function: ActivationFunctionType,
+    recurrent_activation_function: ActivationFunctionType,
+    weights: RecurrentWeights,
+    initial_state: Vec<Array<f32, Ix3>>,
+    cache: Vec<TapeRecord>,
+}
+
+impl GruLayer {
+    pub fn new(
+        input_size: usize,
+        units: usize,
+        return_sequences: bool,
+        activation_function_type: Option<ActivationFunctionType>,
+        recurrent_activation_function_type: Option<ActivationFunctionType>,
+    ) -> Self {
+        Self {
+            input_size,
+            units,
+            return_sequences,
+            activation_function: activation_function_type.unwrap_or(ActivationFunctionType::Tanh),
+            recurrent_activation_function: recurrent_activation_function_type
+                .unwrap_or(ActivationFunctionType::Sigmoid),
+            weights: RecurrentWeights::new(input_size, units, 3),
+            initial_state: Vec::new(),
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        if self.return_sequences {
+            (input_dim.0, 1, self.units)
+        } else {
+            (1, self.units, 1)
+        }
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.activation_function
+    }
+
+    pub fn recurrent_activation_function(&self) -> ActivationFunctionType {
+        self.recurrent_activation_function
+    }
+
+    // Hidden state every sequence starts from, zeros when empty
+    pub fn set_initial_state(
+        &mut self,
+        states: Vec<Array<f32, Ix3>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.initial_state = check_states(states, 1, self.units)?;
+        Ok(())
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input, &self.initial_state)?.0.output()
+    }
+
+    // Runs a sequence from the given state instead of the layer one and also
+    // returns the final hidden state
+    #[expect(clippy::type_complexity)]
+    pub fn forward_with_state(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(Array<f32, Ix3>, Vec<Array<f32, Ix3>>), Box<dyn Error>> {
+        let initial_state = check_states(initial_state.to_vec(), 1, self.units)?;
+        let (record, final_states) = self.record(input, &initial_state)?;
+        Ok((record.output()?, final_states_of(&record, &final_states)?))
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| Ok(self.record(input, &self.initial_state)?.0))
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        recurrent_backward(&self.cache, &mut self.weights, grad_outputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.weights.zero_gradients();
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.weights.parameters()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.weights.parameters_mut()
+    }
+
+    // The recorded sequence along with the final hidden state
+    fn record(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(TapeRecord, Vec<Var>), Box<dyn Error>> {
+        let units = self.units;
+        let mut tape = Tape::new();
+        let (x, rows) = record_projection(&mut tape, input, self.input_size)?;
+        let weights = self.weights.record(&mut tape)?;
+        let projected = projected_with_bias(&mut tape, rows, &weights)?;
+        let mut hidden = record_states(&mut tape, initial_state, 1, units)?[0];
+        let gate_kernel = tape.slice(weights.recurrent_kernel, &[(0, units), (0, 2 * units)])?;
+        let candidate_kernel = tape.slice(
+            weights.recurrent_kernel,
+            &[(0, units), (2 * units, 3 * units)],
+        )?;
+
+        let length = input.dim().0;
+        let mut step_outputs = Vec::with_capacity(length);
+        for step in 0..length {
+            let gate_input = tape.slice(projected, &[(step, step + 1), (0, 2 * units)])?;
+            let candidate_input =
+                tape.slice(projected, &[(step, step + 1), (2 * units, 3 * units)])?;
+
+            let recurrent = tape.matmul(hidden, gate_kernel)?;
+            let gates = tape.add(gate_input, recurrent)?;
+            let gates = tape_activation(&mut tape, gates, self.recurrent_activation_function)?;
+            let update_gate = tape.slice(gates, &[(0, 1), (0, units)])?;
+            let reset_gate = tape.slice(gates, &[(0, 1), (units, 2 * units)])?;
+
+            let reset_hidden = tape.mul(reset_gate, hidden)?;
+            let recurrent = tape.matmul(reset_hidden, candidate_kernel)?;
+            let candidate = tape.add(candidate_input, recurrent)?;
+            let candidate = tape_activation(&mut tape, candidate, self.activation_function)?;
+
+            // z * h + (1 - z) * candidate
+            let kept = tape.mul(update_gate, hidden)?;
+            let negated_update = tape.scale(update_gate, -1.0);
+            let complement = tape.add_scalar(negated_update, 1.0);
+            let written = tape.mul(complement, candidate)?;
+            hidden = tape.add(kept, written)?;
+            step_outputs.push(hidden);
+        }
+
+        let output = sequence_output(&mut tape, &step_outputs, self.return_sequences)?;
+        Ok((
+            TapeRecord {
+                tape,
+                input: x,
+                parameters: weights.leaves,
+                output,
+            },
+            vec![hidden],
+        ))
+    }
+}
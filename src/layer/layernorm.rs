@@ -0,0 +1,147 @@
+use std::error::Error;
+
+use ndarray::{Array, Axis, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord},
+    layer::Parameter,
+};
+
+// Normalizes every sample on its own, with the mean and variance of its
+// features. Features are the values of (1, features, 1) Dense outputs, or
+// the channels of each position of (height, width, channels) and sequence
+// layouts. Backward is derived by the autograd tape.
+pub struct LayerNormLayer {
+    pub features: usize,
+    pub feature_axis: usize,
+    pub epsilon: f32,
+    pub gamma: Array<f32, Ix3>,
+    pub beta: Array<f32, Ix3>,
+    pub gamma_gradient: Array<f32, Ix3>,
+    pub beta_gradient: Array<f32, Ix3>,
+    cache: Vec<TapeRecord>,
+}
+
+impl LayerNormLayer {
+    pub fn for_dense(features: usize, epsilon: Option<f32>) -> Self {
+        Self::new(features, 1, (1, features, 1), epsilon)
+    }
+
+    pub fn for_channels(channels: usize, epsilon: Option<f32>) -> Self {
+        Self::new(channels, 2, (1, 1, channels), epsilon)
+    }
+
+    fn new(
+        features: usize,
+        feature_axis: usize,
+        parameter_dim: (usize, usize, usize),
+        epsilon: Option<f32>,
+    ) -> Self {
+        Self {
+            features,
+            feature_axis,
+            epsilon: epsilon.unwrap_or(1e-3),
+            gamma: Array::ones(parameter_dim),
+            beta: Array::zeros(parameter_dim),
+            gamma_gradient: Array::zeros(parameter_dim),
+            beta_gradient: Array::zeros(parameter_dim),
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input)?.output()
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| self.record(input))
+            .collect::<Result<_, _>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(NormalizationError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (record, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let (grad_input, grad_parameters) = record.backward(grad_output)?;
+            self.gamma_gradient += &grad_parameters[0];
+            self.beta_gradient += &grad_parameters[1];
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.gamma_gradient.fill(0.0);
+        self.beta_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.gamma, &self.beta]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter {
+                value: &mut self.gamma,
+                gradient: &self.gamma_gradient,
+            },
+            Parameter {
+                value: &mut self.beta,
+                gradient: &self.beta_gradient,
+            },
+        ]
+    }
+
+    fn record(&self, input: &Array<f32, Ix3>) -> Result<TapeRecord, Box<dyn Error>> {
+        if input.len_of(Axis(self.feature_axis)) != self.features {
+            return Err(Box::new(NormalizationError::InvalidDimensionsError));
+        }
+        let mut tape = Tape::new();
+        let x = tape.variable(input.clone().into_dyn());
+        let gamma = tape.variable(self.gamma.clone().into_dyn());
+        let beta = tape.variable(self.beta.clone().into_dyn());
+
+        let mean = tape.mean_axis(x, self.feature_axis)?;
+        let centered = tape.sub(x, mean)?;
+        let squared = tape.powi(centered, 2);
+        let variance = tape.mean_axis(squared, self.feature_axis)?;
+        let shifted_variance = tape.add_scalar(variance, self.epsilon);
+        let std = tape.sqrt(shifted_variance);
+        let normalized = tape.div(centered, std)?;
+        let scaled = tape.mul(normalized, gamma)?;
+        let output = tape.add(scaled, beta)?;
+
+        Ok(TapeRecord {
+            tape,
+            input: x,
+            parameters: vec![gamma, beta],
+            output,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NormalizationError {
+    #[error("input features should match the layer features")]
+    InvalidDimensionsError,
+    #[error("channels should be a multiple of groups")]
+    GroupsError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
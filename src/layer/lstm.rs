@@ -0,0 +1,179 @@
+use std::error::Error;
+
+use ndarray::{s, Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord, Var},
+    layer::{
+        recurrent::{
+            check_states, final_states_of, projected_with_bias, record_projection, record_states,
+            recurrent_backward, sequence_output, tape_activation, RecurrentWeights,
+        },
+        Parameter,
+    },
+};
+
+// Long short-term memory over (length, 1, features) sequences, with the
+// input, forget, candidate and output gates stored in that order. States are
+// [hidden, cell]. The forget gate bias starts at one so that the cell state
+// is kept early in training. Backward is derived by the autograd tape.
+pub struct LstmLayer {
+    pub input_size: usize,
+    pub units: usize,
+    pub return_sequences: bool,
+    activation_function: ActivationFunctionType,
+    recurrent_activation_function: ActivationFunctionType,
+    weights: RecurrentWeights,
+    initial_state: Vec<Array<f32, Ix3>>,
+    cache: Vec<TapeRecord>,
+}
+
+impl LstmLayer {
+    pub fn new(
+        input_size: usize,
+        units: usize,
+        return_sequences: bool,
+        activation_function_type: Option<ActivationFunctionType>,
+        recurrent_activation_function_type: Option<ActivationFunctionType>,
+    ) -> Self {
+        let mut weights = RecurrentWeights::new(input_size, units, 4);
+        weights
+            .bias
+            .slice_mut(s![.., units..2 * units, ..])
+            .fill(1.0);
+        Self {
+            input_size,
+            units,
+            return_sequences,
+            activation_function: activation_function_type.unwrap_or(ActivationFunctionType::Tanh),
+            recurrent_activation_function: recurrent_activation_function_type
+                .unwrap_or(ActivationFunctionType::Sigmoid),
+            weights,
+            initial_state: Vec::new(),
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        if self.return_sequences {
+            (input_dim.0, 1, self.units)
+        } else {
+            (1, self.units, 1)
+        }
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.activation_function
+    }
+
+    pub fn recurrent_activation_function(&self) -> ActivationFunctionType {
+        self.recurrent_activation_function
+    }
+
+    // [hidden, cell] states every sequence starts from, zeros when empty
+    pub fn set_initial_state(
+        &mut self,
+        states: Vec<Array<f32, Ix3>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.initial_state = check_states(states, 2, self.units)?;
+        Ok(())
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input, &self.initial_state)?.0.output()
+    }
+
+    // Runs a sequence from the given states instead of the layer ones and
+    // also returns the final [hidden, cell] states
+    #[expect(clippy::type_complexity)]
+    pub fn forward_with_state(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(Array<f32, Ix3>, Vec<Array<f32, Ix3>>), Box<dyn Error>> {
+        let initial_state = check_states(initial_state.to_vec(), 2, self.units)?;
+        let (record, final_states) = self.record(input, &initial_state)?;
+        Ok((record.output()?, final_states_of(&record, &final_states)?))
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| Ok(self.record(input, &self.initial_state)?.0))
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        recurrent_backward(&self.cache, &mut self.weights, grad_outputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.weights.zero_gradients();
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.weights.parameters()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.weights.parameters_mut()
+    }
+
+    // The recorded sequence along with the final [hidden, cell] states
+    fn record(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(TapeRecord, Vec<Var>), Box<dyn Error>> {
+        let units = self.units;
+        let mut tape = Tape::new();
+        let (x, rows) = record_projection(&mut tape, input, self.input_size)?;
+        let weights = self.weights.record(&mut tape)?;
+        let projected = projected_with_bias(&mut tape, rows, &weights)?;
+        let states = record_states(&mut tape, initial_state, 2, units)?;
+        let (mut hidden, mut cell) = (states[0], states[1]);
+
+        let length = input.dim().0;
+        let mut step_outputs = Vec::with_capacity(length);
+        for step in 0..length {
+            let step_input = tape.slice(projected, &[(step, step + 1), (0, 4 * units)])?;
+            let recurrent = tape.matmul(hidden, weights.recurrent_kernel)?;
+            let gates = tape.add(step_input, recurrent)?;
+            let gate = |tape: &mut Tape, index: usize, activation| {
+                let preactivation =
+                    tape.slice(gates, &[(0, 1), (index * units, (index + 1) * units)])?;
+                tape_activation(tape, preactivation, activation)
+            };
+            let input_gate = gate(&mut tape, 0, self.recurrent_activation_function)?;
+            let forget_gate = gate(&mut tape, 1, self.recurrent_activation_function)?;
+            let candidate = gate(&mut tape, 2, self.activation_function)?;
+            let output_gate = gate(&mut tape, 3, self.recurrent_activation_function)?;
+
+            let kept = tape.mul(forget_gate, cell)?;
+            let written = tape.mul(input_gate, candidate)?;
+            cell = tape.add(kept, written)?;
+            let activated_cell = tape_activation(&mut tape, cell, self.activation_function)?;
+            hidden = tape.mul(output_gate, activated_cell)?;
+            step_outputs.push(hidden);
+        }
+
+        let output = sequence_output(&mut tape, &step_outputs, self.return_sequences)?;
+        Ok((
+            TapeRecord {
+                tape,
+                input: x,
+                parameters: weights.leaves,
+                output,
+            },
+            vec![hidden, cell],
+        ))
+    }
+}
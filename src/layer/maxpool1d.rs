@@ -0,0 +1,133 @@
+use std::error::Error;
+
+use ndarray::{s, Array, ArrayView2, Axis, Ix2, Ix3};
+use ndarray_stats::QuantileExt;
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{util::sequence_window, Padding1d},
+};
+
+// Max pooling over (length, 1, channels) sequences. Padding steps are left
+// out of the windows instead of taking part as zeros.
+pub struct MaxPool1dLayer {
+    pub pool_size: usize,
+    pub strides: usize,
+    pub padding: Padding1d,
+    cache: Vec<MaxPool1dCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct MaxPool1dCache {
+    input_length: usize,
+    argmax: Array<usize, Ix2>,
+}
+
+impl MaxPool1dLayer {
+    pub fn new(pool_size: usize, strides: Option<usize>, padding: Option<Padding1d>) -> Self {
+        Self {
+            pool_size,
+            strides: strides.unwrap_or(pool_size),
+            padding: padding.unwrap_or_default(),
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let (input_length, _, channels) = input_dim;
+        let output_length = self
+            .padding
+            .output_length(input_length, self.pool_size, self.strides);
+        (output_length, 1, channels)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (output, _) = self.pool(input)?;
+        Ok(output)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let (output, argmax) = self.pool(input)?;
+            outputs.push(output);
+            self.cache.push(MaxPool1dCache {
+                input_length: input.len_of(Axis(0)),
+                argmax,
+            });
+        }
+        Ok(outputs)
+    }
+
+    // Routes each upstream gradient to the step that was the maximum of its
+    // window
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(Pool1dError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let (output_length, channels) = cache.argmax.dim();
+            if grad_output.dim() != (output_length, 1, channels) {
+                return Err(Box::new(Pool1dError::GradientShapeError));
+            }
+            let mut grad_input = Array::zeros((cache.input_length, 1, channels));
+            for ((step, channel), &position) in cache.argmax.indexed_iter() {
+                grad_input[[position, 0, channel]] += grad_output[[step, 0, channel]];
+            }
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+
+    // Max of every window along with the input step it comes from
+    #[expect(clippy::type_complexity)]
+    fn pool(
+        &self,
+        input: &Array<f32, Ix3>,
+    ) -> Result<(Array<f32, Ix3>, Array<usize, Ix2>), Box<dyn Error>> {
+        let (length, width, channels) = input.dim();
+        if width != 1 {
+            return Err(Box::new(Pool1dError::InvalidDimensionsError));
+        }
+        let (before, _) = self.padding.amounts(length, self.pool_size, self.strides);
+        let (output_length, _, _) = self.output_dim(input.dim());
+
+        let mut output = Array::zeros((output_length, 1, channels));
+        let mut argmax = Array::zeros((output_length, channels));
+        for step in 0..output_length {
+            let steps = sequence_window(step, self.pool_size, self.strides, before, length);
+            let first = steps.start;
+            let window: ArrayView2<f32> = input.slice(s![steps, 0, ..]);
+            for (channel, values) in window.axis_iter(Axis(1)).enumerate() {
+                let position = values.argmax()?;
+                output[[step, 0, channel]] = values[position];
+                argmax[[step, channel]] = first + position;
+            }
+        }
+        Ok((output, argmax))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Pool1dError {
+    #[error("input should be a (length, 1, channels) sequence")]
+    InvalidDimensionsError,
+    #[error("pooling windows should cover at least one step")]
+    EmptyWindowError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -1,14 +1,24 @@
 use std::error::Error;
 
-use ndarray::{s, Array, Axis, Ix3};
+use ndarray::{s, Array, Axis, Dimension, Ix3};
 use ndarray_stats::QuantileExt;
 
-use crate::{activation::ActivationFunctionType, layer::util::add_padding};
+use crate::{
+    activation::ActivationFunctionType,
+    layer::util::{add_padding_with, pool_output_dim, pool_windows, remove_padding},
+};
 
 pub struct MaxPool2dLayer {
     pub pool_size: (usize, usize),
     pub strides: (usize, usize),
     pub padding: (usize, usize),
+    cache: Vec<MaxPool2dCache>,
+}
+
+// Values kept by forward_train for each sample of the batch
+struct MaxPool2dCache {
+    input_dim: Ix3,
+    argmax: Array<(usize, usize), Ix3>,
 }
 
 impl MaxPool2dLayer {
@@ -17,23 +27,18 @@ impl MaxPool2dLayer {
         strides: Option<(usize, usize)>,
         padding: Option<(usize, usize)>,
     ) -> Self {
-        let strides = strides.unwrap_or((1, 1));
+        let strides = strides.unwrap_or(pool_size);
         let padding = padding.unwrap_or((0, 0));
         Self {
             pool_size,
             strides,
             padding,
+            cache: Vec::new(),
         }
     }
 
     pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
-        let (input_height, input_width, input_feature_size) = input_dim;
-        // TODO: strides are not considered by now, add padding
-        (
-            input_height / self.pool_size.0,
-            input_width / self.pool_size.1,
-            input_feature_size,
-        )
+        pool_output_dim(input_dim, self.pool_size, self.strides, self.padding)
     }
 
     pub fn activation_function(&self) -> ActivationFunctionType {
@@ -41,39 +46,96 @@ impl MaxPool2dLayer {
     }
 
     pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
-        let input_padded = add_padding(input, &self.padding);
-        let input_shape = input_padded.shape();
-        let input_padded_height = input_shape[0];
-        let input_padded_width = input_shape[1];
-        let input_padded_feature_size = input_shape[2];
+        let (output, _) = self.pool(input)?;
+        Ok(output)
+    }
 
-        let (output_height, output_width, output_feature_size) = self.output_dim((
-            input_padded_height,
-            input_padded_width,
-            input_padded_feature_size,
-        ));
+    // Same as forward, but remembers which cell won each pooling window for
+    // every sample of the batch. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let (output, argmax) = self.pool(input)?;
+            outputs.push(output);
+            self.cache.push(MaxPool2dCache {
+                input_dim: input.raw_dim(),
+                argmax,
+            });
+        }
+        Ok(outputs)
+    }
 
-        let mut output = Array::zeros((output_height, output_width, output_feature_size));
+    // Routes each upstream gradient to the cell that was the maximum of its
+    // window, every other input cell gets zero
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(MaxPool2dError::MissingForwardError));
+        }
 
-        for (output_row, row) in (0..=input_padded_height - self.pool_size.0)
-            .step_by(self.pool_size.0)
-            .enumerate()
-        {
-            let max_row = row + self.pool_size.0;
-            for (output_col, col) in (0..=input_padded_width - self.pool_size.1)
-                .step_by(self.pool_size.1)
-                .enumerate()
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (cache, grad_output) in self.cache.iter().zip(grad_outputs) {
+            if grad_output.shape() != cache.argmax.shape() {
+                return Err(Box::new(MaxPool2dError::GradientShapeError));
+            }
+            let (input_height, input_width, input_feature_size) = cache.input_dim.into_pattern();
+            let mut grad_input_padded = Array::zeros((
+                input_height + 2 * self.padding.0,
+                input_width + 2 * self.padding.1,
+                input_feature_size,
+            ));
+            for (((_, _, feature), &(row, col)), grad) in
+                cache.argmax.indexed_iter().zip(grad_output)
             {
-                let max_col = col + self.pool_size.1;
-                for feature in 0..input_padded_feature_size {
-                    let max_feature = feature + 1;
-                    let input_slice =
-                        input_padded.slice(s![row..max_row, col..max_col, feature..max_feature]);
-                    output[[output_row, output_col, feature]] =
-                        *input_slice.index_axis(Axis(2), 0).max()?;
-                }
+                grad_input_padded[[row, col, feature]] += grad;
             }
+            grad_inputs.push(remove_padding(&grad_input_padded, &self.padding));
         }
-        Ok(output)
+
+        Ok(grad_inputs)
+    }
+
+    // Max of every window along with its (row, col) in the padded input. The
+    // padding is filled with -inf so that it never wins a window holding an
+    // input cell, and a window lying entirely in the padding gives 0.
+    #[expect(clippy::type_complexity)]
+    fn pool(
+        &self,
+        input: &Array<f32, Ix3>,
+    ) -> Result<(Array<f32, Ix3>, Array<(usize, usize), Ix3>), Box<dyn Error>> {
+        let input_padded = add_padding_with(input, &self.padding, f32::NEG_INFINITY);
+        let output_dim = self.output_dim(input.dim());
+        let mut output = Array::zeros(output_dim);
+        let mut argmax = Array::from_elem(output_dim, (0, 0));
+
+        for ((output_row, output_col), (row, col)) in pool_windows(output_dim, self.strides) {
+            let window = input_padded.slice(s![
+                row..row + self.pool_size.0,
+                col..col + self.pool_size.1,
+                ..
+            ]);
+            for (feature, window) in window.axis_iter(Axis(2)).enumerate() {
+                let (window_row, window_col) = window.argmax()?;
+                let max = window[[window_row, window_col]];
+                output[[output_row, output_col, feature]] =
+                    if max == f32::NEG_INFINITY { 0.0 } else { max };
+                argmax[[output_row, output_col, feature]] = (row + window_row, col + window_col);
+            }
+        }
+        Ok((output, argmax))
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum MaxPool2dError {
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -1,45 +1,509 @@
 use std::error::Error;
 
+use avgpool1d::AvgPool1dLayer;
+use avgpool2d::AvgPool2dLayer;
+use batchnorm::BatchNormLayer;
+use conv1d::Conv1dLayer;
 use conv2d::Conv2dLayer;
+use conv2dtranspose::Conv2dTransposeLayer;
+use custom::CustomLayer;
 use dense::DenseLayer;
+use depthwiseconv2d::DepthwiseConv2dLayer;
+use dropout::DropoutLayer;
+use embedding::EmbeddingLayer;
 use flatten::FlattenLayer;
+use globalpool2d::{GlobalAveragePooling2dLayer, GlobalMaxPooling2dLayer};
+use groupnorm::GroupNormLayer;
+use gru::GruLayer;
+use layernorm::LayerNormLayer;
+use lstm::LstmLayer;
+use maxpool1d::MaxPool1dLayer;
 use maxpool2d::MaxPool2dLayer;
+use multiheadattention::MultiHeadAttentionLayer;
 use ndarray::{Array, Ix3};
+use residual::ResidualLayer;
+use separableconv2d::SeparableConv2dLayer;
+use simplernn::SimpleRnnLayer;
+use transformerencoder::TransformerEncoderBlockLayer;
+use upsampling2d::UpSampling2dLayer;
 
 use crate::activation::ActivationFunctionType;
 
+pub mod avgpool1d;
+pub mod avgpool2d;
+pub mod batchnorm;
+pub mod conv1d;
 pub mod conv2d;
+pub mod conv2dtranspose;
+pub mod custom;
 pub mod dense;
+pub mod depthwiseconv2d;
+pub mod dropout;
+pub mod embedding;
 pub mod flatten;
+pub mod globalpool2d;
+pub mod groupnorm;
+pub mod gru;
+pub mod layernorm;
+pub mod lstm;
+pub mod maxpool1d;
 pub mod maxpool2d;
+pub mod multiheadattention;
+pub mod recurrent;
+pub mod residual;
+pub mod separableconv2d;
+pub mod simplernn;
+pub mod transformerencoder;
+pub mod upsampling2d;
 mod util;
 
+// Layers such as dropout behave differently while training. forward_train
+// always runs in Mode::Training.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Mode {
+    Training,
+    #[default]
+    Inference,
+}
+
+// A trainable tensor of a layer along with its accumulated gradient
+pub struct Parameter<'a> {
+    pub value: &'a mut Array<f32, Ix3>,
+    pub gradient: &'a Array<f32, Ix3>,
+}
+
+// Zeros added around the length of a (length, 1, channels) sequence. Same
+// keeps ceil(length / stride) outputs, Causal only pads the start so that
+// no output sees later steps.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Padding1d {
+    #[default]
+    Valid,
+    Same,
+    Causal,
+}
+
+impl Padding1d {
+    // (before, after) padding for windows spanning `extent` steps
+    pub fn amounts(self, length: usize, extent: usize, stride: usize) -> (usize, usize) {
+        match self {
+            Padding1d::Valid => (0, 0),
+            Padding1d::Same => {
+                let output_length = length.div_ceil(stride);
+                let total = ((output_length.max(1) - 1) * stride + extent).saturating_sub(length);
+                (total / 2, total - total / 2)
+            }
+            Padding1d::Causal => (extent.saturating_sub(1), 0),
+        }
+    }
+
+    pub fn output_length(self, length: usize, extent: usize, stride: usize) -> usize {
+        let (before, after) = self.amounts(length, extent, stride);
+        (length + before + after)
+            .checked_sub(extent)
+            .map_or(0, |span| span / stride + 1)
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
 pub enum Layer {
     Dense(DenseLayer),
     Conv2d(Conv2dLayer),
     MaxPool2d(MaxPool2dLayer),
     Flatten(FlattenLayer),
+    Dropout(DropoutLayer),
+    BatchNorm(BatchNormLayer),
+    LayerNorm(LayerNormLayer),
+    GroupNorm(GroupNormLayer),
+    AvgPool2d(AvgPool2dLayer),
+    GlobalAveragePooling2d(GlobalAveragePooling2dLayer),
+    GlobalMaxPooling2d(GlobalMaxPooling2dLayer),
+    Conv1d(Conv1dLayer),
+    MaxPool1d(MaxPool1dLayer),
+    AvgPool1d(AvgPool1dLayer),
+    Conv2dTranspose(Conv2dTransposeLayer),
+    UpSampling2d(UpSampling2dLayer),
+    DepthwiseConv2d(DepthwiseConv2dLayer),
+    SeparableConv2d(SeparableConv2dLayer),
+    SimpleRnn(SimpleRnnLayer),
+    Lstm(LstmLayer),
+    Gru(GruLayer),
+    MultiHeadAttention(MultiHeadAttentionLayer),
+    TransformerEncoderBlock(TransformerEncoderBlockLayer),
+    Embedding(EmbeddingLayer),
+    Residual(ResidualLayer),
+    Custom(Box<dyn CustomLayer>),
 }
 
 impl Layer {
     // For now, there is only one way of initializing weights
     // fn initialize_weights_with_values<T>(&mut self, values: T);
 
+    // Name of the variant, e.g. for SequentialModel::summary
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Layer::Dense(_) => "Dense",
+            Layer::Conv2d(_) => "Conv2d",
+            Layer::MaxPool2d(_) => "MaxPool2d",
+            Layer::Flatten(_) => "Flatten",
+            Layer::Dropout(_) => "Dropout",
+            Layer::BatchNorm(_) => "BatchNorm",
+            Layer::LayerNorm(_) => "LayerNorm",
+            Layer::GroupNorm(_) => "GroupNorm",
+            Layer::AvgPool2d(_) => "AvgPool2d",
+            Layer::GlobalAveragePooling2d(_) => "GlobalAveragePooling2d",
+            Layer::GlobalMaxPooling2d(_) => "GlobalMaxPooling2d",
+            Layer::Conv1d(_) => "Conv1d",
+            Layer::MaxPool1d(_) => "MaxPool1d",
+            Layer::AvgPool1d(_) => "AvgPool1d",
+            Layer::Conv2dTranspose(_) => "Conv2dTranspose",
+            Layer::UpSampling2d(_) => "UpSampling2d",
+            Layer::DepthwiseConv2d(_) => "DepthwiseConv2d",
+            Layer::SeparableConv2d(_) => "SeparableConv2d",
+            Layer::SimpleRnn(_) => "SimpleRnn",
+            Layer::Lstm(_) => "Lstm",
+            Layer::Gru(_) => "Gru",
+            Layer::MultiHeadAttention(_) => "MultiHeadAttention",
+            Layer::TransformerEncoderBlock(_) => "TransformerEncoderBlock",
+            Layer::Embedding(_) => "Embedding",
+            Layer::Residual(_) => "Residual",
+            Layer::Custom(custom) => custom.type_name(),
+        }
+    }
+
     pub fn activation_function(&self) -> ActivationFunctionType {
         match &self {
             Layer::Dense(dense) => dense.activation_function(),
             Layer::Conv2d(conv) => conv.activation_function(),
             Layer::MaxPool2d(max_pool) => max_pool.activation_function(),
             Layer::Flatten(flatten) => flatten.activation_function(),
+            Layer::Dropout(dropout) => dropout.activation_function(),
+            Layer::BatchNorm(batch_norm) => batch_norm.activation_function(),
+            Layer::LayerNorm(layer_norm) => layer_norm.activation_function(),
+            Layer::GroupNorm(group_norm) => group_norm.activation_function(),
+            Layer::AvgPool2d(avg_pool) => avg_pool.activation_function(),
+            Layer::GlobalAveragePooling2d(global_pool) => global_pool.activation_function(),
+            Layer::GlobalMaxPooling2d(global_pool) => global_pool.activation_function(),
+            Layer::Conv1d(conv) => conv.activation_function(),
+            Layer::MaxPool1d(max_pool) => max_pool.activation_function(),
+            Layer::AvgPool1d(avg_pool) => avg_pool.activation_function(),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.activation_function(),
+            Layer::UpSampling2d(up_sampling) => up_sampling.activation_function(),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.activation_function(),
+            Layer::SeparableConv2d(separable) => separable.activation_function(),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.activation_function(),
+            Layer::Lstm(lstm) => lstm.activation_function(),
+            Layer::Gru(gru) => gru.activation_function(),
+            Layer::MultiHeadAttention(attention) => attention.activation_function(),
+            Layer::TransformerEncoderBlock(transformer) => transformer.activation_function(),
+            Layer::Embedding(embedding) => embedding.activation_function(),
+            Layer::Residual(residual) => residual.activation_function(),
+            Layer::Custom(custom) => custom.activation_function(),
         }
     }
 
-    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+    // Shape of the output for an input of shape input_dim, None when the
+    // layer cannot take such an input
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> Option<(usize, usize, usize)> {
+        let (height, width, channels) = input_dim;
+        let is_sequence = |features: usize| width == 1 && channels == features;
+        if height == 0 || width == 0 || channels == 0 {
+            return None;
+        }
+        let output_dim = match self {
+            Layer::Dense(dense) => {
+                (input_dim == (1, dense.input_size, 1)).then_some((1, dense.output_size, 1))
+            }
+            Layer::Conv2d(conv) => conv.accepts(input_dim).then_some(conv.output_dim),
+            Layer::MaxPool2d(max_pool) => Some(max_pool.output_dim(input_dim)),
+            Layer::Flatten(_) => Some((1, height * width * channels, 1)),
+            Layer::Dropout(_) => Some(input_dim),
+            Layer::BatchNorm(batch_norm) => {
+                let features = match batch_norm.feature_axis {
+                    1 if height == 1 && channels == 1 => width,
+                    2 => channels,
+                    _ => 0,
+                };
+                (features == batch_norm.features).then_some(input_dim)
+            }
+            Layer::LayerNorm(layer_norm) => {
+                let features = [height, width, channels][layer_norm.feature_axis];
+                (features == layer_norm.features).then_some(input_dim)
+            }
+            Layer::GroupNorm(group_norm) => (channels == group_norm.channels).then_some(input_dim),
+            Layer::AvgPool2d(avg_pool) => Some(avg_pool.output_dim(input_dim)),
+            Layer::GlobalAveragePooling2d(global_pool) => Some(global_pool.output_dim(input_dim)),
+            Layer::GlobalMaxPooling2d(global_pool) => Some(global_pool.output_dim(input_dim)),
+            Layer::Conv1d(conv) => is_sequence(conv.channels).then(|| conv.output_dim(input_dim)),
+            Layer::MaxPool1d(max_pool) => (width == 1).then(|| max_pool.output_dim(input_dim)),
+            Layer::AvgPool1d(avg_pool) => (width == 1).then(|| avg_pool.output_dim(input_dim)),
+            Layer::Conv2dTranspose(conv_transpose) => {
+                (channels == conv_transpose.channels).then(|| conv_transpose.output_dim(input_dim))
+            }
+            Layer::UpSampling2d(up_sampling) => Some(up_sampling.output_dim(input_dim)),
+            Layer::DepthwiseConv2d(depthwise) => {
+                depthwise.accepts(input_dim).then(|| depthwise.output_dim())
+            }
+            Layer::SeparableConv2d(separable) => {
+                separable.accepts(input_dim).then(|| separable.output_dim())
+            }
+            Layer::SimpleRnn(simple_rnn) => {
+                is_sequence(simple_rnn.input_size).then(|| simple_rnn.output_dim(input_dim))
+            }
+            Layer::Lstm(lstm) => is_sequence(lstm.input_size).then(|| lstm.output_dim(input_dim)),
+            Layer::Gru(gru) => is_sequence(gru.input_size).then(|| gru.output_dim(input_dim)),
+            Layer::MultiHeadAttention(attention) => {
+                is_sequence(attention.model_size).then_some(input_dim)
+            }
+            Layer::TransformerEncoderBlock(transformer) => {
+                is_sequence(transformer.attention().model_size).then_some(input_dim)
+            }
+            Layer::Embedding(embedding) => Some(embedding.output_dim(input_dim)),
+            Layer::Residual(residual) => residual.output_dim(input_dim),
+            Layer::Custom(custom) => custom.output_dim(input_dim),
+        };
+        // e.g. pooling windows larger than the input
+        output_dim.filter(|&(height, width, channels)| height > 0 && width > 0 && channels > 0)
+    }
+
+    pub fn forward(
+        &self,
+        input: &Array<f32, Ix3>,
+        mode: Mode,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
         match &self {
             Layer::Dense(dense) => dense.forward(input),
             Layer::Conv2d(conv) => conv.forward(input),
             Layer::MaxPool2d(max_pool) => max_pool.forward(input),
             Layer::Flatten(flatten) => flatten.forward(input),
+            Layer::Dropout(dropout) => dropout.forward(input, mode),
+            Layer::BatchNorm(batch_norm) => batch_norm.forward(input),
+            Layer::LayerNorm(layer_norm) => layer_norm.forward(input),
+            Layer::GroupNorm(group_norm) => group_norm.forward(input),
+            Layer::AvgPool2d(avg_pool) => avg_pool.forward(input),
+            Layer::GlobalAveragePooling2d(global_pool) => global_pool.forward(input),
+            Layer::GlobalMaxPooling2d(global_pool) => global_pool.forward(input),
+            Layer::Conv1d(conv) => conv.forward(input),
+            Layer::MaxPool1d(max_pool) => max_pool.forward(input),
+            Layer::AvgPool1d(avg_pool) => avg_pool.forward(input),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.forward(input),
+            Layer::UpSampling2d(up_sampling) => up_sampling.forward(input),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.forward(input),
+            Layer::SeparableConv2d(separable) => separable.forward(input),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.forward(input),
+            Layer::Lstm(lstm) => lstm.forward(input),
+            Layer::Gru(gru) => gru.forward(input),
+            Layer::MultiHeadAttention(attention) => attention.forward(input),
+            Layer::TransformerEncoderBlock(transformer) => transformer.forward(input),
+            Layer::Embedding(embedding) => embedding.forward(input),
+            Layer::Residual(residual) => residual.forward(input, mode),
+            Layer::Custom(custom) => custom.forward(input, mode),
+        }
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        match self {
+            Layer::Dense(dense) => dense.forward_train(inputs),
+            Layer::Conv2d(conv) => conv.forward_train(inputs),
+            Layer::MaxPool2d(max_pool) => max_pool.forward_train(inputs),
+            Layer::Flatten(flatten) => flatten.forward_train(inputs),
+            Layer::Dropout(dropout) => dropout.forward_train(inputs),
+            Layer::BatchNorm(batch_norm) => batch_norm.forward_train(inputs),
+            Layer::LayerNorm(layer_norm) => layer_norm.forward_train(inputs),
+            Layer::GroupNorm(group_norm) => group_norm.forward_train(inputs),
+            Layer::AvgPool2d(avg_pool) => avg_pool.forward_train(inputs),
+            Layer::GlobalAveragePooling2d(global_pool) => global_pool.forward_train(inputs),
+            Layer::GlobalMaxPooling2d(global_pool) => global_pool.forward_train(inputs),
+            Layer::Conv1d(conv) => conv.forward_train(inputs),
+            Layer::MaxPool1d(max_pool) => max_pool.forward_train(inputs),
+            Layer::AvgPool1d(avg_pool) => avg_pool.forward_train(inputs),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.forward_train(inputs),
+            Layer::UpSampling2d(up_sampling) => up_sampling.forward_train(inputs),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.forward_train(inputs),
+            Layer::SeparableConv2d(separable) => separable.forward_train(inputs),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.forward_train(inputs),
+            Layer::Lstm(lstm) => lstm.forward_train(inputs),
+            Layer::Gru(gru) => gru.forward_train(inputs),
+            Layer::MultiHeadAttention(attention) => attention.forward_train(inputs),
+            Layer::TransformerEncoderBlock(transformer) => transformer.forward_train(inputs),
+            Layer::Embedding(embedding) => embedding.forward_train(inputs),
+            Layer::Residual(residual) => residual.forward_train(inputs),
+            Layer::Custom(custom) => custom.forward_train(inputs),
+        }
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        match self {
+            Layer::Dense(dense) => dense.backward(grad_outputs),
+            Layer::Conv2d(conv) => conv.backward(grad_outputs),
+            Layer::MaxPool2d(max_pool) => max_pool.backward(grad_outputs),
+            Layer::Flatten(flatten) => flatten.backward(grad_outputs),
+            Layer::Dropout(dropout) => dropout.backward(grad_outputs),
+            Layer::BatchNorm(batch_norm) => batch_norm.backward(grad_outputs),
+            Layer::LayerNorm(layer_norm) => layer_norm.backward(grad_outputs),
+            Layer::GroupNorm(group_norm) => group_norm.backward(grad_outputs),
+            Layer::AvgPool2d(avg_pool) => avg_pool.backward(grad_outputs),
+            Layer::GlobalAveragePooling2d(global_pool) => global_pool.backward(grad_outputs),
+            Layer::GlobalMaxPooling2d(global_pool) => global_pool.backward(grad_outputs),
+            Layer::Conv1d(conv) => conv.backward(grad_outputs),
+            Layer::MaxPool1d(max_pool) => max_pool.backward(grad_outputs),
+            Layer::AvgPool1d(avg_pool) => avg_pool.backward(grad_outputs),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.backward(grad_outputs),
+            Layer::UpSampling2d(up_sampling) => up_sampling.backward(grad_outputs),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.backward(grad_outputs),
+            Layer::SeparableConv2d(separable) => separable.backward(grad_outputs),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.backward(grad_outputs),
+            Layer::Lstm(lstm) => lstm.backward(grad_outputs),
+            Layer::Gru(gru) => gru.backward(grad_outputs),
+            Layer::MultiHeadAttention(attention) => attention.backward(grad_outputs),
+            Layer::TransformerEncoderBlock(transformer) => transformer.backward(grad_outputs),
+            Layer::Embedding(embedding) => embedding.backward(grad_outputs),
+            Layer::Residual(residual) => residual.backward(grad_outputs),
+            Layer::Custom(custom) => custom.backward(grad_outputs),
+        }
+    }
+
+    pub fn zero_gradients(&mut self) {
+        match self {
+            Layer::Dense(dense) => dense.zero_gradients(),
+            Layer::Conv2d(conv) => conv.zero_gradients(),
+            Layer::BatchNorm(batch_norm) => batch_norm.zero_gradients(),
+            Layer::LayerNorm(layer_norm) => layer_norm.zero_gradients(),
+            Layer::GroupNorm(group_norm) => group_norm.zero_gradients(),
+            Layer::MaxPool2d(_)
+            | Layer::Flatten(_)
+            | Layer::Dropout(_)
+            | Layer::AvgPool2d(_)
+            | Layer::GlobalAveragePooling2d(_)
+            | Layer::GlobalMaxPooling2d(_)
+            | Layer::MaxPool1d(_)
+            | Layer::AvgPool1d(_)
+            | Layer::UpSampling2d(_) => {}
+            Layer::Conv1d(conv) => conv.zero_gradients(),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.zero_gradients(),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.zero_gradients(),
+            Layer::SeparableConv2d(separable) => separable.zero_gradients(),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.zero_gradients(),
+            Layer::Lstm(lstm) => lstm.zero_gradients(),
+            Layer::Gru(gru) => gru.zero_gradients(),
+            Layer::MultiHeadAttention(attention) => attention.zero_gradients(),
+            Layer::TransformerEncoderBlock(transformer) => transformer.zero_gradients(),
+            Layer::Embedding(embedding) => embedding.zero_gradients(),
+            Layer::Residual(residual) => residual.zero_gradients(),
+            Layer::Custom(custom) => custom.zero_gradients(),
+        }
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        match self {
+            Layer::Dense(dense) => dense.parameters(),
+            Layer::Conv2d(conv) => conv.parameters(),
+            Layer::BatchNorm(batch_norm) => batch_norm.parameters(),
+            Layer::LayerNorm(layer_norm) => layer_norm.parameters(),
+            Layer::GroupNorm(group_norm) => group_norm.parameters(),
+            Layer::MaxPool2d(_)
+            | Layer::Flatten(_)
+            | Layer::Dropout(_)
+            | Layer::AvgPool2d(_)
+            | Layer::GlobalAveragePooling2d(_)
+            | Layer::GlobalMaxPooling2d(_)
+            | Layer::MaxPool1d(_)
+            | Layer::AvgPool1d(_)
+            | Layer::UpSampling2d(_) => Vec::new(),
+            Layer::Conv1d(conv) => conv.parameters(),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.parameters(),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.parameters(),
+            Layer::SeparableConv2d(separable) => separable.parameters(),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.parameters(),
+            Layer::Lstm(lstm) => lstm.parameters(),
+            Layer::Gru(gru) => gru.parameters(),
+            Layer::MultiHeadAttention(attention) => attention.parameters(),
+            Layer::TransformerEncoderBlock(transformer) => transformer.parameters(),
+            Layer::Embedding(embedding) => embedding.parameters(),
+            Layer::Residual(residual) => residual.parameters(),
+            Layer::Custom(custom) => custom.parameters(),
+        }
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        match self {
+            Layer::Dense(dense) => dense.parameters_mut(),
+            Layer::Conv2d(conv) => conv.parameters_mut(),
+            Layer::BatchNorm(batch_norm) => batch_norm.parameters_mut(),
+            Layer::LayerNorm(layer_norm) => layer_norm.parameters_mut(),
+            Layer::GroupNorm(group_norm) => group_norm.parameters_mut(),
+            Layer::MaxPool2d(_)
+            | Layer::Flatten(_)
+            | Layer::Dropout(_)
+            | Layer::AvgPool2d(_)
+            | Layer::GlobalAveragePooling2d(_)
+            | Layer::GlobalMaxPooling2d(_)
+            | Layer::MaxPool1d(_)
+            | Layer::AvgPool1d(_)
+            | Layer::UpSampling2d(_) => Vec::new(),
+            Layer::Conv1d(conv) => conv.parameters_mut(),
+            Layer::Conv2dTranspose(conv_transpose) => conv_transpose.parameters_mut(),
+            Layer::DepthwiseConv2d(depthwise) => depthwise.parameters_mut(),
+            Layer::SeparableConv2d(separable) => separable.parameters_mut(),
+            Layer::SimpleRnn(simple_rnn) => simple_rnn.parameters_mut(),
+            Layer::Lstm(lstm) => lstm.parameters_mut(),
+            Layer::Gru(gru) => gru.parameters_mut(),
+            Layer::MultiHeadAttention(attention) => attention.parameters_mut(),
+            Layer::TransformerEncoderBlock(transformer) => transformer.parameters_mut(),
+            Layer::Embedding(embedding) => embedding.parameters_mut(),
+            Layer::Residual(residual) => residual.parameters_mut(),
+            Layer::Custom(custom) => custom.parameters_mut(),
+        }
+    }
+
+    // State that is not trained by gradients but should be saved along with
+    // the parameters, such as running statistics
+    pub fn buffers(&self) -> Vec<&Array<f32, Ix3>> {
+        match self {
+            Layer::BatchNorm(batch_norm) => batch_norm.buffers(),
+            Layer::Residual(residual) => residual.buffers(),
+            Layer::Custom(custom) => custom.buffers(),
+            Layer::Dense(_)
+            | Layer::Conv2d(_)
+            | Layer::MaxPool2d(_)
+            | Layer::Flatten(_)
+            | Layer::Dropout(_)
+            | Layer::LayerNorm(_)
+            | Layer::GroupNorm(_)
+            | Layer::AvgPool2d(_)
+            | Layer::GlobalAveragePooling2d(_)
+            | Layer::GlobalMaxPooling2d(_)
+            | Layer::Conv1d(_)
+            | Layer::MaxPool1d(_)
+            | Layer::AvgPool1d(_)
+            | Layer::Conv2dTranspose(_)
+            | Layer::UpSampling2d(_)
+            | Layer::DepthwiseConv2d(_)
+            | Layer::SeparableConv2d(_)
+            | Layer::SimpleRnn(_)
+            | Layer::Lstm(_)
+            | Layer::Gru(_)
+            | Layer::MultiHeadAttention(_)
+            | Layer::TransformerEncoderBlock(_)
+            | Layer::Embedding(_) => Vec::new(),
+        }
+    }
+
+    // Both at once, as they borrow the same layer
+    pub fn parameters_and_buffers_mut(
+        &mut self,
+    ) -> (Vec<Parameter<'_>>, Vec<&mut Array<f32, Ix3>>) {
+        match self {
+            Layer::BatchNorm(batch_norm) => batch_norm.parameters_and_buffers_mut(),
+            Layer::Residual(residual) => residual.parameters_and_buffers_mut(),
+            Layer::Custom(custom) => custom.parameters_and_buffers_mut(),
+            layer => (layer.parameters_mut(), Vec::new()),
         }
     }
 }
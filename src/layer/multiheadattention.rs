@@ -0,0 +1,248 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix2, Ix3};
+use ndarray_rand::RandomExt;
+use rand::{distributions::Uniform, Rng};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord, Var},
+    layer::Parameter,
+};
+
+// Added to the scores of masked pairs, small enough for softmax to ignore
+// them without overflowing
+const MASKED_SCORE: f32 = -1e9;
+
+// Multi-head self-attention over (length, 1, model_size) sequences. Every
+// head attends with its own model_size / heads columns of the query, key and
+// value projections, and the heads are joined by an output projection.
+// Masks are (length, length) arrays where true lets a query step (row) attend
+// to a key step (column). Backward is derived by the autograd tape.
+pub struct MultiHeadAttentionLayer {
+    pub model_size: usize,
+    pub heads: usize,
+    pub causal: bool,
+    mask: Option<Array<bool, Ix2>>,
+    // query, key, value and output projections, each followed by its bias
+    weights: Vec<Array<f32, Ix3>>,
+    gradients: Vec<Array<f32, Ix3>>,
+    cache: Vec<TapeRecord>,
+}
+
+impl MultiHeadAttentionLayer {
+    pub fn new(model_size: usize, heads: usize, causal: bool) -> Result<Self, Box<dyn Error>> {
+        Self::new_using(model_size, heads, causal, &mut rand::thread_rng())
+    }
+
+    // Same as new, drawing the projections from rng
+    #[expect(clippy::cast_precision_loss)]
+    pub fn new_using<R: Rng + ?Sized>(
+        model_size: usize,
+        heads: usize,
+        causal: bool,
+        rng: &mut R,
+    ) -> Result<Self, Box<dyn Error>> {
+        if heads == 0 || !model_size.is_multiple_of(heads) {
+            return Err(Box::new(AttentionError::HeadsError));
+        }
+        let limit = (3.0 / model_size as f32).sqrt();
+        let mut weights = Vec::with_capacity(8);
+        for _ in 0..4 {
+            weights.push(Array::random_using(
+                (model_size, model_size, 1),
+                Uniform::new_inclusive(-limit, limit),
+                rng,
+            ));
+            weights.push(Array::zeros((1, model_size, 1)));
+        }
+        let gradients = weights.iter().map(|w| Array::zeros(w.raw_dim())).collect();
+        Ok(Self {
+            model_size,
+            heads,
+            causal,
+            mask: None,
+            weights,
+            gradients,
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        input_dim
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    // Mask applied to every sequence, on top of the causal one
+    pub fn set_mask(&mut self, mask: Option<Array<bool, Ix2>>) {
+        self.mask = mask;
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input, self.mask.as_ref())?.output()
+    }
+
+    // Same as forward with a mask for this sequence only, e.g. to hide the
+    // padding steps of a batch of sequences of different lengths
+    pub fn forward_with_mask(
+        &self,
+        input: &Array<f32, Ix3>,
+        mask: &Array<bool, Ix2>,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input, Some(mask))?.output()
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| self.record(input, self.mask.as_ref()))
+            .collect::<Result<_, _>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(AttentionError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (record, grad_output) in self.cache.iter().zip(grad_outputs) {
+            let (grad_input, grad_parameters) = record.backward(grad_output)?;
+            for (gradient, grad_parameter) in self.gradients.iter_mut().zip(&grad_parameters) {
+                *gradient += grad_parameter;
+            }
+            grad_inputs.push(grad_input);
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        for gradient in &mut self.gradients {
+            gradient.fill(0.0);
+        }
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.weights.iter().collect()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.weights
+            .iter_mut()
+            .zip(&self.gradients)
+            .map(|(value, gradient)| Parameter { value, gradient })
+            .collect()
+    }
+
+    // Zero where attending is allowed and MASKED_SCORE elsewhere
+    fn score_offsets(
+        &self,
+        length: usize,
+        mask: Option<&Array<bool, Ix2>>,
+    ) -> Result<Array<f32, Ix2>, Box<dyn Error>> {
+        let mut offsets = Array::zeros((length, length));
+        if let Some(mask) = mask {
+            if mask.dim() != (length, length) {
+                return Err(Box::new(AttentionError::MaskShapeError));
+            }
+            offsets.zip_mut_with(mask, |offset, attend| {
+                if !attend {
+                    *offset = MASKED_SCORE;
+                }
+            });
+        }
+        if self.causal {
+            for ((query, key), offset) in offsets.indexed_iter_mut() {
+                if key > query {
+                    *offset = MASKED_SCORE;
+                }
+            }
+        }
+        Ok(offsets)
+    }
+
+    // x·W + b of one projection, as a (length, model_size) matrix
+    fn project(
+        tape: &mut Tape,
+        rows: Var,
+        weights: &[Var],
+        projection: usize,
+    ) -> Result<Var, Box<dyn Error>> {
+        let product = tape.matmul(rows, weights[2 * projection])?;
+        tape.add(product, weights[2 * projection + 1])
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    fn record(
+        &self,
+        input: &Array<f32, Ix3>,
+        mask: Option<&Array<bool, Ix2>>,
+    ) -> Result<TapeRecord, Box<dyn Error>> {
+        let (length, width, model_size) = input.dim();
+        if width != 1 || model_size != self.model_size {
+            return Err(Box::new(AttentionError::InvalidDimensionsError));
+        }
+        let head_size = model_size / self.heads;
+
+        let mut tape = Tape::new();
+        let x = tape.variable(input.clone().into_dyn());
+        let rows = tape.reshape(x, &[length, model_size])?;
+        let mut leaves = Vec::with_capacity(self.weights.len());
+        let mut matrices = Vec::with_capacity(self.weights.len());
+        for weights in &self.weights {
+            let (weight_rows, weight_columns, _) = weights.dim();
+            let leaf = tape.variable(weights.clone().into_dyn());
+            matrices.push(tape.reshape(leaf, &[weight_rows, weight_columns])?);
+            leaves.push(leaf);
+        }
+        let offsets = tape.variable(self.score_offsets(length, mask)?.into_dyn());
+
+        let queries = Self::project(&mut tape, rows, &matrices, 0)?;
+        let keys = Self::project(&mut tape, rows, &matrices, 1)?;
+        let values = Self::project(&mut tape, rows, &matrices, 2)?;
+        let mut head_outputs = Vec::with_capacity(self.heads);
+        for head in 0..self.heads {
+            let columns = (head * head_size, (head + 1) * head_size);
+            let head_queries = tape.slice(queries, &[(0, length), columns])?;
+            let head_keys = tape.slice(keys, &[(0, length), columns])?;
+            let head_values = tape.slice(values, &[(0, length), columns])?;
+
+            let transposed_keys = tape.transpose(head_keys);
+            let scores = tape.matmul(head_queries, transposed_keys)?;
+            let scaled_scores = tape.scale(scores, 1.0 / (head_size as f32).sqrt());
+            let masked_scores = tape.add(scaled_scores, offsets)?;
+            let attention = tape.softmax(masked_scores, 1)?;
+            head_outputs.push(tape.matmul(attention, head_values)?);
+        }
+        let joined = tape.concat(&head_outputs, 1)?;
+        let projected = Self::project(&mut tape, joined, &matrices, 3)?;
+        let output = tape.reshape(projected, &[length, 1, model_size])?;
+
+        Ok(TapeRecord {
+            tape,
+            input: x,
+            parameters: leaves,
+            output,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AttentionError {
+    #[error("model size should be a multiple of the number of heads")]
+    HeadsError,
+    #[error("input should be a (length, 1, model_size) sequence")]
+    InvalidDimensionsError,
+    #[error("mask should be a (length, length) array")]
+    MaskShapeError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
@@ -0,0 +1,249 @@
+// building blocks shared by the recurrent layers
+//
+// SimpleRnn, Lstm and Gru record every sample on its own autograd tape: the
+// (length, 1, features) input is projected for all steps at once, then the
+// recurrence unrolls step by step from the initial states and backward is
+// derived by the tape.
+
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+use ndarray_rand::RandomExt;
+use rand::distributions::Uniform;
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeError, TapeRecord, Var},
+    layer::Parameter,
+};
+
+// Activation recorded on a tape. Softmax runs over the last axis, the
+// features of a row.
+pub fn tape_activation(
+    tape: &mut Tape,
+    var: Var,
+    activation_function: ActivationFunctionType,
+) -> Result<Var, Box<dyn Error>> {
+    Ok(match activation_function {
+        ActivationFunctionType::None => var,
+        ActivationFunctionType::Relu => tape.relu(var),
+        ActivationFunctionType::LeakyRelu => tape.leaky_relu(var, 0.1),
+        ActivationFunctionType::Sigmoid => tape.sigmoid(var),
+        ActivationFunctionType::Tanh => tape.tanh(var),
+        ActivationFunctionType::Softmax => {
+            let last_axis = tape.value(var).ndim() - 1;
+            tape.softmax(var, last_axis)?
+        }
+    })
+}
+
+// Input, recurrent and bias weights of a recurrent layer with `gates` blocks
+// of `units` columns, stored like Dense weights as (rows, columns, 1)
+pub struct RecurrentWeights {
+    pub kernel: Array<f32, Ix3>,
+    pub recurrent_kernel: Array<f32, Ix3>,
+    pub bias: Array<f32, Ix3>,
+    pub kernel_gradient: Array<f32, Ix3>,
+    pub recurrent_kernel_gradient: Array<f32, Ix3>,
+    pub bias_gradient: Array<f32, Ix3>,
+}
+
+// Leaves of the weights on a tape, followed by their 2-D views
+pub struct RecordedWeights {
+    pub leaves: Vec<Var>,
+    pub kernel: Var,
+    pub recurrent_kernel: Var,
+    pub bias: Var,
+}
+
+impl RecurrentWeights {
+    // Uniform in +-1/sqrt(units), which keeps the state from saturating
+    #[expect(clippy::cast_precision_loss)]
+    pub fn new(input_size: usize, units: usize, gates: usize) -> Self {
+        let limit = 1.0 / (units.max(1) as f32).sqrt();
+        let distribution = Uniform::new_inclusive(-limit, limit);
+        let columns = gates * units;
+        Self {
+            kernel: Array::random((input_size, columns, 1), distribution),
+            recurrent_kernel: Array::random((units, columns, 1), distribution),
+            bias: Array::zeros((1, columns, 1)),
+            kernel_gradient: Array::zeros((input_size, columns, 1)),
+            recurrent_kernel_gradient: Array::zeros((units, columns, 1)),
+            bias_gradient: Array::zeros((1, columns, 1)),
+        }
+    }
+
+    pub fn record(&self, tape: &mut Tape) -> Result<RecordedWeights, Box<dyn Error>> {
+        let mut leaves = Vec::with_capacity(3);
+        let mut matrices = Vec::with_capacity(3);
+        for weights in [&self.kernel, &self.recurrent_kernel, &self.bias] {
+            let (rows, columns, _) = weights.dim();
+            let leaf = tape.variable(weights.clone().into_dyn());
+            matrices.push(tape.reshape(leaf, &[rows, columns])?);
+            leaves.push(leaf);
+        }
+        Ok(RecordedWeights {
+            leaves,
+            kernel: matrices[0],
+            recurrent_kernel: matrices[1],
+            bias: matrices[2],
+        })
+    }
+
+    // Adds the gradients of the leaves, in the order of record
+    pub fn accumulate(&mut self, gradients: &[Array<f32, Ix3>]) {
+        self.kernel_gradient += &gradients[0];
+        self.recurrent_kernel_gradient += &gradients[1];
+        self.bias_gradient += &gradients[2];
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.kernel_gradient.fill(0.0);
+        self.recurrent_kernel_gradient.fill(0.0);
+        self.bias_gradient.fill(0.0);
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        vec![&self.kernel, &self.recurrent_kernel, &self.bias]
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        vec![
+            Parameter {
+                value: &mut self.kernel,
+                gradient: &self.kernel_gradient,
+            },
+            Parameter {
+                value: &mut self.recurrent_kernel,
+                gradient: &self.recurrent_kernel_gradient,
+            },
+            Parameter {
+                value: &mut self.bias,
+                gradient: &self.bias_gradient,
+            },
+        ]
+    }
+}
+
+// (1, units) state rows on a tape, from (1, units, 1) arrays or zeros when
+// no state is given
+pub fn record_states(
+    tape: &mut Tape,
+    states: &[Array<f32, Ix3>],
+    count: usize,
+    units: usize,
+) -> Result<Vec<Var>, Box<dyn Error>> {
+    (0..count)
+        .map(|index| {
+            let state = states
+                .get(index)
+                .map_or_else(|| Array::zeros((1, units, 1)), Clone::clone);
+            let leaf = tape.variable(state.into_dyn());
+            tape.reshape(leaf, &[1, units])
+        })
+        .collect()
+}
+
+// Stacks every step output into (length, 1, units), or keeps the last one as a
+// (1, units, 1) vector ready for a Dense layer
+pub fn sequence_output(
+    tape: &mut Tape,
+    step_outputs: &[Var],
+    return_sequences: bool,
+) -> Result<Var, Box<dyn Error>> {
+    let last = *step_outputs.last().ok_or(TapeError::EmptyAxisError)?;
+    let units = tape.value(last).len();
+    if return_sequences {
+        let steps = tape.concat(step_outputs, 0)?;
+        tape.reshape(steps, &[step_outputs.len(), 1, units])
+    } else {
+        tape.reshape(last, &[1, units, 1])
+    }
+}
+
+// Records a (length, 1, features) input and its projection x·W, without bias
+pub fn record_projection(
+    tape: &mut Tape,
+    input: &Array<f32, Ix3>,
+    input_size: usize,
+) -> Result<(Var, Var), Box<dyn Error>> {
+    let (length, width, features) = input.dim();
+    if width != 1 || features != input_size {
+        return Err(Box::new(RecurrentError::InvalidDimensionsError));
+    }
+    if length == 0 {
+        return Err(Box::new(RecurrentError::EmptySequenceError));
+    }
+    let x = tape.variable(input.clone().into_dyn());
+    let rows = tape.reshape(x, &[length, features])?;
+    Ok((x, rows))
+}
+
+// x·W + b for every step at once
+pub fn projected_with_bias(
+    tape: &mut Tape,
+    rows: Var,
+    weights: &RecordedWeights,
+) -> Result<Var, Box<dyn Error>> {
+    let projected = tape.matmul(rows, weights.kernel)?;
+    tape.add(projected, weights.bias)
+}
+
+// Checks that states are (1, units, 1) and that there are `count` of them
+pub fn check_states(
+    states: Vec<Array<f32, Ix3>>,
+    count: usize,
+    units: usize,
+) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    let valid = states.is_empty()
+        || (states.len() == count && states.iter().all(|state| state.dim() == (1, units, 1)));
+    if valid {
+        Ok(states)
+    } else {
+        Err(Box::new(RecurrentError::StateError))
+    }
+}
+
+// Values of the final (1, units) states as (1, units, 1) arrays
+pub fn final_states_of(
+    record: &TapeRecord,
+    states: &[Var],
+) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    states
+        .iter()
+        .map(|state| {
+            let value = record.tape.value(*state);
+            Ok(value.to_shape((1, value.len(), 1))?.to_owned())
+        })
+        .collect()
+}
+
+// Backward of recurrent layers whose only parameters are their weights
+pub fn recurrent_backward(
+    cache: &[TapeRecord],
+    weights: &mut RecurrentWeights,
+    grad_outputs: &[Array<f32, Ix3>],
+) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    if grad_outputs.len() != cache.len() {
+        return Err(Box::new(RecurrentError::MissingForwardError));
+    }
+    let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+    for (record, grad_output) in cache.iter().zip(grad_outputs) {
+        let (grad_input, grad_parameters) = record.backward(grad_output)?;
+        weights.accumulate(&grad_parameters);
+        grad_inputs.push(grad_input);
+    }
+    Ok(grad_inputs)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecurrentError {
+    #[error("input should be a (length, 1, features) sequence with the layer features")]
+    InvalidDimensionsError,
+    #[error("sequences should have at least one step")]
+    EmptySequenceError,
+    #[error("initial states should be (1, units, 1) arrays, one per layer state")]
+    StateError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
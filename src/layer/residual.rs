@@ -0,0 +1,195 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{Layer, Mode, Parameter},
+};
+
+// Skip connection around a stack of layers: output = layers(x) + shortcut(x).
+// The shortcut is the input itself, or a projection (usually a Conv2d with a
+// kernel size of 1, or a Dense for dense vectors) when the layers change the
+// shape of their input.
+pub struct ResidualLayer {
+    layers: Vec<Layer>,
+    projection: Option<Box<Layer>>,
+    cache: Vec<Ix3>,
+}
+
+impl ResidualLayer {
+    pub fn new(layers: Vec<Layer>, projection: Option<Layer>) -> Result<Self, Box<dyn Error>> {
+        if layers.is_empty() {
+            return Err(Box::new(ResidualError::EmptyLayersError));
+        }
+        Ok(Self {
+            layers,
+            projection: projection.map(Box::new),
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn projection(&self) -> Option<&Layer> {
+        self.projection.as_deref()
+    }
+
+    // None when a wrapped layer cannot take its input or when the shortcut
+    // does not have the shape of the layers output
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> Option<(usize, usize, usize)> {
+        let mut output_dim = input_dim;
+        for layer in &self.layers {
+            output_dim = layer.output_dim(output_dim)?;
+        }
+        let shortcut_dim = match &self.projection {
+            Some(projection) => projection.output_dim(input_dim)?,
+            None => input_dim,
+        };
+        (shortcut_dim == output_dim).then_some(output_dim)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(
+        &self,
+        input: &Array<f32, Ix3>,
+        mode: Mode,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let mut output = input.clone();
+        for layer in &self.layers {
+            output = layer.forward(&output, mode)?;
+        }
+        let shortcut = match &self.projection {
+            Some(projection) => projection.forward(input, mode)?,
+            None => input.clone(),
+        };
+        add_shortcut(output, &shortcut)
+    }
+
+    // Same as forward, but every wrapped layer keeps what it needs for
+    // backward. The cache is replaced on each call.
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache.clear();
+        let mut outputs = inputs.to_vec();
+        for layer in &mut self.layers {
+            outputs = layer.forward_train(&outputs)?;
+        }
+        let shortcuts = match &mut self.projection {
+            Some(projection) => projection.forward_train(inputs)?,
+            None => inputs.to_vec(),
+        };
+
+        let outputs = outputs
+            .into_iter()
+            .zip(&shortcuts)
+            .map(|(output, shortcut)| add_shortcut(output, shortcut))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.cache = outputs.iter().map(Array::raw_dim).collect();
+        Ok(outputs)
+    }
+
+    // The gradient of the output flows unchanged to both branches, and the
+    // input gradient is the sum of theirs
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.cache.len() {
+            return Err(Box::new(ResidualError::MissingForwardError));
+        }
+        if grad_outputs
+            .iter()
+            .zip(&self.cache)
+            .any(|(grad_output, output_dim)| grad_output.raw_dim() != *output_dim)
+        {
+            return Err(Box::new(ResidualError::GradientShapeError));
+        }
+
+        let mut grad_inputs = grad_outputs.to_vec();
+        for layer in self.layers.iter_mut().rev() {
+            grad_inputs = layer.backward(&grad_inputs)?;
+        }
+        let grad_shortcuts = match &mut self.projection {
+            Some(projection) => projection.backward(grad_outputs)?,
+            None => grad_outputs.to_vec(),
+        };
+        for (grad_input, grad_shortcut) in grad_inputs.iter_mut().zip(&grad_shortcuts) {
+            *grad_input += grad_shortcut;
+        }
+        Ok(grad_inputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        for layer in self.layers_mut() {
+            layer.zero_gradients();
+        }
+    }
+
+    // Parameters of the wrapped layers, then the ones of the projection
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.layers
+            .iter()
+            .chain(self.projection.as_deref())
+            .flat_map(Layer::parameters)
+            .collect()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.layers_mut().flat_map(Layer::parameters_mut).collect()
+    }
+
+    pub fn buffers(&self) -> Vec<&Array<f32, Ix3>> {
+        self.layers
+            .iter()
+            .chain(self.projection.as_deref())
+            .flat_map(Layer::buffers)
+            .collect()
+    }
+
+    pub fn parameters_and_buffers_mut(
+        &mut self,
+    ) -> (Vec<Parameter<'_>>, Vec<&mut Array<f32, Ix3>>) {
+        let mut parameters = Vec::new();
+        let mut buffers = Vec::new();
+        for layer in self.layers_mut() {
+            let (layer_parameters, layer_buffers) = layer.parameters_and_buffers_mut();
+            parameters.extend(layer_parameters);
+            buffers.extend(layer_buffers);
+        }
+        (parameters, buffers)
+    }
+
+    fn layers_mut(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.layers.iter_mut().chain(self.projection.as_deref_mut())
+    }
+}
+
+fn add_shortcut(
+    output: Array<f32, Ix3>,
+    shortcut: &Array<f32, Ix3>,
+) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+    if output.shape() != shortcut.shape() {
+        return Err(Box::new(ResidualError::ShortcutShapeError));
+    }
+    Ok(output + shortcut)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResidualError {
+    #[error("a residual block needs at least one layer")]
+    EmptyLayersError,
+    #[error("the shortcut should have the shape of the layers output, a projection may be needed")]
+    ShortcutShapeError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
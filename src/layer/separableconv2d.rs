@@ -0,0 +1,103 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{conv2d::Conv2dLayer, depthwiseconv2d::DepthwiseConv2dLayer, Parameter},
+};
+
+// Depthwise convolution followed by a 1x1 convolution mixing the channels,
+// the building block of MobileNet. The activation is only applied after the
+// pointwise step.
+pub struct SeparableConv2dLayer {
+    depthwise: DepthwiseConv2dLayer,
+    pointwise: Conv2dLayer,
+}
+
+impl SeparableConv2dLayer {
+    #[expect(clippy::too_many_arguments)]
+    pub fn new(
+        filters: usize,
+        kernel_size: usize,
+        input_dim: (usize, usize, usize),
+        depth_multiplier: Option<usize>,
+        padding: Option<(usize, usize)>,
+        strides: Option<(usize, usize)>,
+        dilation_rate: Option<(usize, usize)>,
+        activation_function_type: Option<ActivationFunctionType>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let depthwise = DepthwiseConv2dLayer::new(
+            kernel_size,
+            input_dim,
+            depth_multiplier,
+            padding,
+            strides,
+            dilation_rate,
+            None,
+        )?;
+        let pointwise = Conv2dLayer::new(
+            filters,
+            1,
+            depthwise.output_dim(),
+            None,
+            None,
+            None,
+            activation_function_type,
+        )?;
+        Ok(Self {
+            depthwise,
+            pointwise,
+        })
+    }
+
+    pub fn output_dim(&self) -> (usize, usize, usize) {
+        self.pointwise.output_dim
+    }
+
+    pub fn accepts(&self, input_dim: (usize, usize, usize)) -> bool {
+        self.depthwise.accepts(input_dim)
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.pointwise.activation_function()
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.pointwise.forward(&self.depthwise.forward(input)?)
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        let depthwise_outputs = self.depthwise.forward_train(inputs)?;
+        self.pointwise.forward_train(&depthwise_outputs)
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        let grad_depthwise_outputs = self.pointwise.backward(grad_outputs)?;
+        self.depthwise.backward(&grad_depthwise_outputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.depthwise.zero_gradients();
+        self.pointwise.zero_gradients();
+    }
+
+    // Depthwise kernels first, then the pointwise ones
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        let mut parameters = self.depthwise.parameters();
+        parameters.extend(self.pointwise.parameters());
+        parameters
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        let mut parameters = self.depthwise.parameters_mut();
+        parameters.extend(self.pointwise.parameters_mut());
+        parameters
+    }
+}
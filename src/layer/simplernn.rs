@@ -0,0 +1,151 @@
+use std::error::Error;
+
+use ndarray::{Array, Ix3};
+
+use crate::{
+    activation::ActivationFunctionType,
+    autograd::{Tape, TapeRecord, Var},
+    layer::{
+        recurrent::{
+            check_states, final_states_of, projected_with_bias, record_projection, record_states,
+            recurrent_backward, sequence_output, tape_activation, RecurrentWeights,
+        },
+        Parameter,
+    },
+};
+
+// Fully connected recurrence h = activation(x·W + h·U + b) over
+// (length, 1, features) sequences. With return_sequences the output holds
+// every step as (length, 1, units), otherwise the last state as (1, units, 1).
+// Backward is derived by the autograd tape.
+pub struct SimpleRnnLayer {
+    pub input_size: usize,
+    pub units: usize,
+    pub return_sequences: bool,
+    activation_function: ActivationFunctionType,
+    weights: RecurrentWeights,
+    initial_state: Vec<Array<f32, Ix3>>,
+    cache: Vec<TapeRecord>,
+}
+
+impl SimpleRnnLayer {
+    pub fn new(
+        input_size: usize,
+        units: usize,
+        return_sequences: bool,
+        activation_function_type: Option<ActivationFunctionType>,
+    ) -> Self {
+        Self {
+            input_size,
+            units,
+            return_sequences,
+            activation_function: activation_function_type.unwrap_or(ActivationFunctionType::Tanh),
+            weights: RecurrentWeights::new(input_size, units, 1),
+            initial_state: Vec::new(),
+            cache: Vec::new(),
+        }
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        if self.return_sequences {
+            (input_dim.0, 1, self.units)
+        } else {
+            (1, self.units, 1)
+        }
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        self.activation_function
+    }
+
+    // States every sequence starts from, zeros when empty. SimpleRnn has a
+    // single (1, units, 1) hidden state.
+    pub fn set_initial_state(
+        &mut self,
+        states: Vec<Array<f32, Ix3>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.initial_state = check_states(states, 1, self.units)?;
+        Ok(())
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.record(input, &self.initial_state)?.0.output()
+    }
+
+    // Runs a sequence from the given states instead of the layer ones and
+    // also returns the final states, e.g. to start a decoder
+    #[expect(clippy::type_complexity)]
+    pub fn forward_with_state(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(Array<f32, Ix3>, Vec<Array<f32, Ix3>>), Box<dyn Error>> {
+        let initial_state = check_states(initial_state.to_vec(), 1, self.units)?;
+        let (record, final_states) = self.record(input, &initial_state)?;
+        Ok((record.output()?, final_states_of(&record, &final_states)?))
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.cache = inputs
+            .iter()
+            .map(|input| Ok(self.record(input, &self.initial_state)?.0))
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        self.cache.iter().map(TapeRecord::output).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        recurrent_backward(&self.cache, &mut self.weights, grad_outputs)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.weights.zero_gradients();
+    }
+
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.weights.parameters()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.weights.parameters_mut()
+    }
+
+    // The recorded sequence along with the final hidden state
+    fn record(
+        &self,
+        input: &Array<f32, Ix3>,
+        initial_state: &[Array<f32, Ix3>],
+    ) -> Result<(TapeRecord, Vec<Var>), Box<dyn Error>> {
+        let mut tape = Tape::new();
+        let (x, projected) = record_projection(&mut tape, input, self.input_size)?;
+        let weights = self.weights.record(&mut tape)?;
+        let projected = projected_with_bias(&mut tape, projected, &weights)?;
+        let mut hidden = record_states(&mut tape, initial_state, 1, self.units)?[0];
+
+        let length = input.dim().0;
+        let mut step_outputs = Vec::with_capacity(length);
+        for step in 0..length {
+            let step_input = tape.slice(projected, &[(step, step + 1), (0, self.units)])?;
+            let recurrent = tape.matmul(hidden, weights.recurrent_kernel)?;
+            let preactivation = tape.add(step_input, recurrent)?;
+            hidden = tape_activation(&mut tape, preactivation, self.activation_function)?;
+            step_outputs.push(hidden);
+        }
+
+        let output = sequence_output(&mut tape, &step_outputs, self.return_sequences)?;
+        Ok((
+            TapeRecord {
+                tape,
+                input: x,
+                parameters: weights.leaves,
+                output,
+            },
+            vec![hidden],
+        ))
+    }
+}
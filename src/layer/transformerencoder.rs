@@ -0,0 +1,213 @@
+use std::error::Error;
+
+use ndarray::{Array, Axis, Ix2, Ix3};
+use rand::Rng;
+
+use crate::{
+    activation::ActivationFunctionType,
+    layer::{
+        dense::DenseLayer, layernorm::LayerNormLayer, multiheadattention::MultiHeadAttentionLayer,
+        Parameter,
+    },
+};
+
+// Post-norm encoder block over (length, 1, model_size) sequences:
+//   x = norm(x + attention(x))
+//   x = norm(x + dense(relu(dense(x))))
+// The feed-forward DenseLayers run on every step on its own, as a batch of
+// (1, model_size, 1) vectors.
+pub struct TransformerEncoderBlockLayer {
+    attention: MultiHeadAttentionLayer,
+    attention_norm: LayerNormLayer,
+    feed_forward_hidden: DenseLayer,
+    feed_forward_output: DenseLayer,
+    feed_forward_norm: LayerNormLayer,
+    lengths: Vec<usize>,
+}
+
+impl TransformerEncoderBlockLayer {
+    pub fn new(
+        model_size: usize,
+        heads: usize,
+        feed_forward_size: usize,
+        causal: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::new_using(
+            model_size,
+            heads,
+            feed_forward_size,
+            causal,
+            &mut rand::thread_rng(),
+        )
+    }
+
+    // Same as new, drawing the attention and feed-forward weights from rng
+    pub fn new_using<R: Rng + ?Sized>(
+        model_size: usize,
+        heads: usize,
+        feed_forward_size: usize,
+        causal: bool,
+        rng: &mut R,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            attention: MultiHeadAttentionLayer::new_using(model_size, heads, causal, rng)?,
+            attention_norm: LayerNormLayer::for_channels(model_size, None),
+            feed_forward_hidden: DenseLayer::new_using(
+                model_size,
+                feed_forward_size,
+                Some(ActivationFunctionType::Relu),
+                rng,
+            ),
+            feed_forward_output: DenseLayer::new_using(feed_forward_size, model_size, None, rng),
+            feed_forward_norm: LayerNormLayer::for_channels(model_size, None),
+            lengths: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        input_dim
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn attention(&self) -> &MultiHeadAttentionLayer {
+        &self.attention
+    }
+
+    pub fn set_mask(&mut self, mask: Option<Array<bool, Ix2>>) {
+        self.attention.set_mask(mask);
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let attended = input + &self.attention.forward(input)?;
+        let attended = self.attention_norm.forward(&attended)?;
+        let mut feed_forward = Vec::with_capacity(attended.len_of(Axis(0)));
+        for step in steps(std::slice::from_ref(&attended))? {
+            let hidden = self.feed_forward_hidden.forward(&step)?;
+            feed_forward.push(self.feed_forward_output.forward(&hidden)?);
+        }
+        let feed_forward = sequences(&feed_forward, &[attended.len_of(Axis(0))])?;
+        self.feed_forward_norm
+            .forward(&(attended + &feed_forward[0]))
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.lengths = inputs.iter().map(|input| input.len_of(Axis(0))).collect();
+        let attention_outputs = self.attention.forward_train(inputs)?;
+        let residuals: Vec<_> = inputs
+            .iter()
+            .zip(&attention_outputs)
+            .map(|(input, output)| input + output)
+            .collect();
+        let attended = self.attention_norm.forward_train(&residuals)?;
+
+        let hidden = self.feed_forward_hidden.forward_train(&steps(&attended)?)?;
+        let feed_forward = self.feed_forward_output.forward_train(&hidden)?;
+        let feed_forward = sequences(&feed_forward, &self.lengths)?;
+        let residuals: Vec<_> = attended
+            .iter()
+            .zip(&feed_forward)
+            .map(|(attended, feed_forward)| attended + feed_forward)
+            .collect();
+        self.feed_forward_norm.forward_train(&residuals)
+    }
+
+    // Residual connections add their upstream gradient to the one coming
+    // back through the branch
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.lengths.len() {
+            return Err(Box::new(TransformerError::MissingForwardError));
+        }
+        let grad_residuals = self.feed_forward_norm.backward(grad_outputs)?;
+        let grad_hidden = self
+            .feed_forward_output
+            .backward(&steps(&grad_residuals)?)?;
+        let grad_steps = self.feed_forward_hidden.backward(&grad_hidden)?;
+        let grad_attended: Vec<_> = sequences(&grad_steps, &self.lengths)?
+            .into_iter()
+            .zip(&grad_residuals)
+            .map(|(grad_branch, grad_residual)| grad_branch + grad_residual)
+            .collect();
+
+        let grad_residuals = self.attention_norm.backward(&grad_attended)?;
+        let grad_branch = self.attention.backward(&grad_residuals)?;
+        Ok(grad_branch
+            .into_iter()
+            .zip(&grad_residuals)
+            .map(|(grad_branch, grad_residual)| grad_branch + grad_residual)
+            .collect())
+    }
+
+    pub fn zero_gradients(&mut self) {
+        self.attention.zero_gradients();
+        self.attention_norm.zero_gradients();
+        self.feed_forward_hidden.zero_gradients();
+        self.feed_forward_output.zero_gradients();
+        self.feed_forward_norm.zero_gradients();
+    }
+
+    // Attention, its norm, both feed-forward layers and their norm, in that
+    // order
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        let mut parameters = self.attention.parameters();
+        parameters.extend(self.attention_norm.parameters());
+        parameters.extend(self.feed_forward_hidden.parameters());
+        parameters.extend(self.feed_forward_output.parameters());
+        parameters.extend(self.feed_forward_norm.parameters());
+        parameters
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        let mut parameters = self.attention.parameters_mut();
+        parameters.extend(self.attention_norm.parameters_mut());
+        parameters.extend(self.feed_forward_hidden.parameters_mut());
+        parameters.extend(self.feed_forward_output.parameters_mut());
+        parameters.extend(self.feed_forward_norm.parameters_mut());
+        parameters
+    }
+}
+
+// Every step of every (length, 1, size) sequence as a (1, size, 1) vector
+fn steps(sequences: &[Array<f32, Ix3>]) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    let mut steps = Vec::new();
+    for sequence in sequences {
+        let (_, _, size) = sequence.dim();
+        for step in sequence.outer_iter() {
+            steps.push(step.to_shape((1, size, 1))?.to_owned());
+        }
+    }
+    Ok(steps)
+}
+
+// Inverse of steps
+fn sequences(
+    steps: &[Array<f32, Ix3>],
+    lengths: &[usize],
+) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    let mut sequences = Vec::with_capacity(lengths.len());
+    let mut first = 0;
+    for &length in lengths {
+        let size = steps.get(first).map_or(0, Array::len);
+        let mut sequence = Array::zeros((length, 1, size));
+        for (mut row, step) in sequence.outer_iter_mut().zip(&steps[first..first + length]) {
+            row.assign(&step.to_shape((1, size))?);
+        }
+        sequences.push(sequence);
+        first += length;
+    }
+    Ok(sequences)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransformerError {
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+}
@@ -0,0 +1,141 @@
+use std::error::Error;
+
+use ndarray::{Array, ArrayView2, Axis, Ix2, Ix3};
+
+use crate::activation::ActivationFunctionType;
+
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum Interpolation {
+    #[default]
+    Nearest,
+    Bilinear,
+}
+
+// Enlarges the height and width of (height, width, channels) inputs by an
+// integer factor. Both interpolations are separable, so every channel is
+// resized as rows · channel · colsᵀ with two interpolation matrices, and
+// backward is the same products transposed.
+pub struct UpSampling2dLayer {
+    pub size: (usize, usize),
+    pub interpolation: Interpolation,
+    input_dims: Vec<(usize, usize, usize)>,
+}
+
+impl UpSampling2dLayer {
+    pub fn new(
+        size: (usize, usize),
+        interpolation: Option<Interpolation>,
+    ) -> Result<Self, Box<dyn Error>> {
+        if size.0 < 1 || size.1 < 1 {
+            return Err(Box::new(UpSampling2dError::SizeError));
+        }
+        Ok(Self {
+            size,
+            interpolation: interpolation.unwrap_or_default(),
+            input_dims: Vec::new(),
+        })
+    }
+
+    pub fn output_dim(&self, input_dim: (usize, usize, usize)) -> (usize, usize, usize) {
+        let (input_height, input_width, channels) = input_dim;
+        (
+            input_height * self.size.0,
+            input_width * self.size.1,
+            channels,
+        )
+    }
+
+    pub fn activation_function(&self) -> ActivationFunctionType {
+        ActivationFunctionType::None
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        let (height, width, _) = input.dim();
+        let rows = self.interpolation_matrix(height, self.size.0);
+        let cols = self.interpolation_matrix(width, self.size.1);
+        Ok(resize(
+            input,
+            rows.view(),
+            cols.view(),
+            self.output_dim(input.dim()),
+        ))
+    }
+
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.input_dims = inputs.iter().map(Array::dim).collect();
+        inputs.iter().map(|input| self.forward(input)).collect()
+    }
+
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        if grad_outputs.len() != self.input_dims.len() {
+            return Err(Box::new(UpSampling2dError::MissingForwardError));
+        }
+        let mut grad_inputs = Vec::with_capacity(grad_outputs.len());
+        for (&input_dim, grad_output) in self.input_dims.iter().zip(grad_outputs) {
+            if grad_output.dim() != self.output_dim(input_dim) {
+                return Err(Box::new(UpSampling2dError::GradientShapeError));
+            }
+            let (height, width, _) = input_dim;
+            let rows = self.interpolation_matrix(height, self.size.0);
+            let cols = self.interpolation_matrix(width, self.size.1);
+            grad_inputs.push(resize(grad_output, rows.t(), cols.t(), input_dim));
+        }
+        Ok(grad_inputs)
+    }
+
+    // (length * factor, length) weights of the input cells read by every
+    // output cell. Bilinear samples at the centers of the output cells, the
+    // way most frameworks do without corner alignment.
+    #[expect(clippy::cast_precision_loss)]
+    #[expect(clippy::cast_possible_truncation)]
+    #[expect(clippy::cast_sign_loss)]
+    fn interpolation_matrix(&self, length: usize, factor: usize) -> Array<f32, Ix2> {
+        let mut matrix = Array::zeros((length * factor, length));
+        for (output, mut weights) in matrix.outer_iter_mut().enumerate() {
+            match self.interpolation {
+                Interpolation::Nearest => weights[output / factor] = 1.0,
+                Interpolation::Bilinear => {
+                    let position = ((output as f32 + 0.5) / factor as f32 - 0.5)
+                        .clamp(0.0, (length - 1) as f32);
+                    let first = position.floor() as usize;
+                    let second = (first + 1).min(length - 1);
+                    let fraction = position - position.floor();
+                    weights[first] += 1.0 - fraction;
+                    weights[second] += fraction;
+                }
+            }
+        }
+        matrix
+    }
+}
+
+// rows · channel · colsᵀ for every channel
+fn resize(
+    input: &Array<f32, Ix3>,
+    rows: ArrayView2<f32>,
+    cols: ArrayView2<f32>,
+    output_dim: (usize, usize, usize),
+) -> Array<f32, Ix3> {
+    let mut output = Array::zeros(output_dim);
+    for (channel, mut output_channel) in output.axis_iter_mut(Axis(2)).enumerate() {
+        let input_channel = input.index_axis(Axis(2), channel);
+        output_channel.assign(&rows.dot(&input_channel).dot(&cols.t()));
+    }
+    output
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpSampling2dError {
+    #[error("upsampling size should be at least 1")]
+    SizeError,
+    #[error("backward needs a forward_train call with the same batch size")]
+    MissingForwardError,
+    #[error("gradient shape should match the layer output")]
+    GradientShapeError,
+}
@@ -1,24 +1,102 @@
+use std::ops::Range;
+
 use ndarray::{s, Array, Ix3};
 
+// Zero pads the height and width of a (height, width, channels) array on both
+// sides
 pub fn add_padding(input: &Array<f32, Ix3>, padding: &(usize, usize)) -> Array<f32, Ix3> {
+    add_padding_with(input, padding, 0.0)
+}
+
+// Same as add_padding, filling the padding with value
+pub fn add_padding_with(
+    input: &Array<f32, Ix3>,
+    padding: &(usize, usize),
+    value: f32,
+) -> Array<f32, Ix3> {
     let input_shape = input.shape();
-    let input_channel_size = input_shape[0];
-    let input_height = input_shape[1];
-    let input_width = input_shape[2];
+    let input_height = input_shape[0];
+    let input_width = input_shape[1];
+    let input_channel_size = input_shape[2];
 
-    let mut input_padded = Array::zeros((
-        input_channel_size,
-        input_height + padding.0,
-        input_width + padding.1,
-    ));
+    let mut input_padded = Array::from_elem(
+        (
+            input_height + 2 * padding.0,
+            input_width + 2 * padding.1,
+            input_channel_size,
+        ),
+        value,
+    );
 
     input_padded
         .slice_mut(s![
-            0..input_channel_size,
-            padding.0..input_height,
-            padding.1..input_width
+            padding.0..padding.0 + input_height,
+            padding.1..padding.1 + input_width,
+            0..input_channel_size
         ])
         .assign(input);
 
     input_padded
 }
+
+// Inverse of add_padding, used to bring gradients back to the unpadded shape
+pub fn remove_padding(input: &Array<f32, Ix3>, padding: &(usize, usize)) -> Array<f32, Ix3> {
+    let input_shape = input.shape();
+    input
+        .slice(s![
+            padding.0..input_shape[0] - padding.0,
+            padding.1..input_shape[1] - padding.1,
+            ..
+        ])
+        .to_owned()
+}
+
+// Output (height, width, channels) of a pooling layer for an unpadded input
+pub fn pool_output_dim(
+    input_dim: (usize, usize, usize),
+    pool_size: (usize, usize),
+    strides: (usize, usize),
+    padding: (usize, usize),
+) -> (usize, usize, usize) {
+    let (input_height, input_width, input_channels) = input_dim;
+    let windows = |size: usize, pool: usize, stride: usize, pad: usize| {
+        (size + 2 * pad)
+            .checked_sub(pool)
+            .map_or(0, |span| span / stride + 1)
+    };
+    (
+        windows(input_height, pool_size.0, strides.0, padding.0),
+        windows(input_width, pool_size.1, strides.1, padding.1),
+        input_channels,
+    )
+}
+
+// Top-left (row, col) in the padded input of every pooling window, along
+// with the (row, col) of the output cell it produces
+pub fn pool_windows(
+    output_dim: (usize, usize, usize),
+    strides: (usize, usize),
+) -> impl Iterator<Item = ((usize, usize), (usize, usize))> {
+    let (output_height, output_width, _) = output_dim;
+    (0..output_height).flat_map(move |output_row| {
+        (0..output_width).map(move |output_col| {
+            (
+                (output_row, output_col),
+                (output_row * strides.0, output_col * strides.1),
+            )
+        })
+    })
+}
+
+// Steps of a `length` sequence covered by the pooling window of the output
+// `step`, leaving out the `before` padding steps and the ones after the end
+pub fn sequence_window(
+    step: usize,
+    pool_size: usize,
+    stride: usize,
+    before: usize,
+    length: usize,
+) -> Range<usize> {
+    let first = step * stride;
+    first.max(before) - before..(first + pool_size).min(before + length) - before
+}
@@ -1,25 +1,69 @@
 #![expect(dead_code)]
 pub mod activation;
+pub mod autograd;
+pub mod callback;
+pub mod gradcheck;
 pub mod layer;
+pub mod loss;
 pub mod model;
+pub mod optim;
 
 #[cfg(test)]
 mod tests {
     use std::f32;
 
     use approx::assert_relative_eq;
-    use more_asserts::{assert_ge, assert_le};
-    use ndarray::array;
-    //use ndarray_rand::RandomExt;
+    use more_asserts::{assert_ge, assert_le, assert_lt};
+    use ndarray::{array, s, Array, Axis, Ix3};
+    use ndarray_rand::RandomExt;
     use plotpy::{Curve, Plot};
+    use rand::{distributions::Uniform, rngs::StdRng, seq::SliceRandom, SeedableRng};
 
     use crate::{
-        activation::{relu, sigmoid, ActivationFunctionType},
+        activation::{relu, sigmoid, softmax, ActivationFunctionType},
+        autograd::Tape,
+        callback::{
+            checkpoint::ModelCheckpoint, csv_logger::CsvLogger, early_stopping::EarlyStopping,
+        },
+        gradcheck::{check_gradients, check_gradients_using},
         layer::{
-            conv2d::Conv2dLayer, dense::DenseLayer, flatten::FlattenLayer,
-            maxpool2d::MaxPool2dLayer, Layer,
+            avgpool1d::AvgPool1dLayer,
+            avgpool2d::AvgPool2dLayer,
+            batchnorm::BatchNormLayer,
+            conv1d::Conv1dLayer,
+            conv2d::Conv2dLayer,
+            conv2dtranspose::Conv2dTransposeLayer,
+            custom::CustomLayer,
+            dense::DenseLayer,
+            depthwiseconv2d::DepthwiseConv2dLayer,
+            dropout::DropoutLayer,
+            embedding::EmbeddingLayer,
+            flatten::FlattenLayer,
+            globalpool2d::{GlobalAveragePooling2dLayer, GlobalMaxPooling2dLayer},
+            groupnorm::GroupNormLayer,
+            gru::GruLayer,
+            layernorm::LayerNormLayer,
+            lstm::LstmLayer,
+            maxpool1d::MaxPool1dLayer,
+            maxpool2d::MaxPool2dLayer,
+            multiheadattention::MultiHeadAttentionLayer,
+            residual::ResidualLayer,
+            separableconv2d::SeparableConv2dLayer,
+            simplernn::SimpleRnnLayer,
+            transformerencoder::TransformerEncoderBlockLayer,
+            upsampling2d::{Interpolation, UpSampling2dLayer},
+            Layer, Mode, Padding1d, Parameter,
+        },
+        loss::{
+            categorical_cross_entropy, categorical_cross_entropy_from_logits, huber,
+            mean_absolute_error, mean_squared_error, LossFunctionType,
+        },
+        model::{
+            functional::{FunctionalModel, Merge},
+            sequential::SequentialModel,
+            weights::WeightsError,
         },
-        model::sequential::SequentialModel,
+        optim::{scheduler::LearningRateScheduler, Optimizer},
     };
 
     #[test]
@@ -41,6 +85,47 @@ mod tests {
         assert_relative_eq!(result_minus_6, 0.002_472_623);
     }
 
+    #[test]
+    fn loss_functions_work() {
+        let predictions = array![[[0.5], [2.0], [-1.0]]];
+        let targets = array![[[1.0], [2.0], [1.0]]];
+
+        let (loss, gradient) = mean_squared_error(&predictions, &targets).unwrap();
+        assert_relative_eq!(loss, (0.25 + 4.0) / 3.0);
+        assert_relative_eq!(gradient[[0, 2, 0]], -4.0 / 3.0);
+
+        let (loss, gradient) = huber(&predictions, &targets, 1.0).unwrap();
+        assert_relative_eq!(loss, (0.125 + 1.5) / 3.0);
+        assert_relative_eq!(gradient[[0, 0, 0]], -0.5 / 3.0);
+        assert_relative_eq!(gradient[[0, 2, 0]], -1.0 / 3.0);
+
+        assert!(mean_absolute_error(&predictions, &array![[[1.0]]]).is_err());
+    }
+
+    #[test]
+    fn cross_entropy_from_logits_matches_softmax() {
+        let logits = array![[[1.0], [3.0], [-2.0]]];
+        let one_hot = array![[[0.0], [1.0], [0.0]]];
+        let probabilities = softmax(&logits);
+
+        let (fused_loss, fused_gradient) =
+            categorical_cross_entropy_from_logits(&logits, &one_hot).unwrap();
+        let (loss, _) = categorical_cross_entropy(&probabilities, &one_hot).unwrap();
+        let (sparse_loss, _) = LossFunctionType::SparseCategoricalCrossEntropy
+            .compute(&probabilities, &array![[[1.0]]])
+            .unwrap();
+        assert_relative_eq!(fused_loss, loss, epsilon = 1e-5);
+        assert_relative_eq!(sparse_loss, loss, epsilon = 1e-5);
+        for ((gradient, p), t) in fused_gradient.iter().zip(&probabilities).zip(&one_hot) {
+            assert_relative_eq!(*gradient, p - t, epsilon = 1e-6);
+        }
+
+        // logits this large overflow exp() when softmax is computed separately
+        let (large_loss, _) =
+            categorical_cross_entropy_from_logits(&(logits * 100.0), &one_hot).unwrap();
+        assert!(large_loss.is_finite());
+    }
+
     #[test]
     fn dense_works() {
         let nn = DenseLayer::new(2, 1, None);
@@ -69,6 +154,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dense_backward() {
+        let mut nn = DenseLayer::new(3, 2, Some(ActivationFunctionType::Sigmoid));
+        let input = array![[[0.5], [-1.0], [2.0]]];
+        let outputs = nn.forward_train(std::slice::from_ref(&input)).unwrap();
+        assert_eq!(outputs[0], nn.forward(&input).unwrap());
+
+        // with loss = sum(output), every upstream gradient is 1
+        let grad_inputs = nn.backward(&[Array::ones((1, 2, 1))]).unwrap();
+        assert_eq!(grad_inputs[0].shape(), input.shape());
+
+        let epsilon = 1e-2;
+        for i in 0..3 {
+            let mut shifted = input.clone();
+            shifted[[0, i, 0]] += epsilon;
+            let numeric = (nn.forward(&shifted).unwrap().sum() - outputs[0].sum()) / epsilon;
+            assert_relative_eq!(grad_inputs[0][[0, i, 0]], numeric, epsilon = 1e-2);
+        }
+
+        let grad_preactivation = outputs[0].map(|s| s * (1.0 - s));
+        for (gradient, expected) in nn.bias_gradient.iter().zip(&grad_preactivation) {
+            assert_relative_eq!(gradient, expected, epsilon = 1e-6);
+        }
+        assert_relative_eq!(
+            nn.weights_gradient[[2, 1, 0]],
+            2.0 * grad_preactivation[[0, 1, 0]],
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn tape_matches_dense_backward() {
+        let mut nn = DenseLayer::new(3, 2, Some(ActivationFunctionType::Sigmoid));
+        let input = array![[[0.5], [-1.0], [2.0]]];
+        nn.forward_train(std::slice::from_ref(&input)).unwrap();
+        let grad_input = &nn.backward(&[array![[[1.0], [-2.0]]]]).unwrap()[0];
+
+        let mut tape = Tape::new();
+        let x = tape.variable(input.to_shape((1, 3)).unwrap().to_owned().into_dyn());
+        let weights = tape.variable(nn.weights.to_shape((3, 2)).unwrap().to_owned().into_dyn());
+        let bias = tape.variable(nn.bias.to_shape((1, 2)).unwrap().to_owned().into_dyn());
+        let product = tape.matmul(x, weights).unwrap();
+        let preactivation = tape.add(product, bias).unwrap();
+        let output = tape.sigmoid(preactivation);
+        let gradients = tape
+            .backward_with(output, array![[1.0, -2.0]].into_dyn())
+            .unwrap();
+
+        let tape_pairs = [
+            (gradients.get(x).unwrap(), grad_input),
+            (gradients.get(weights).unwrap(), &nn.weights_gradient),
+            (gradients.get(bias).unwrap(), &nn.bias_gradient),
+        ];
+        for (tape_gradient, layer_gradient) in tape_pairs {
+            for (a, b) in tape_gradient.iter().zip(layer_gradient) {
+                assert_relative_eq!(a, b, epsilon = 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn tape_shape_operations() {
+        let input = array![[0.3, -1.2, 0.7], [1.5, 0.1, -0.4]].into_dyn();
+        let loss = |tape: &mut Tape, x| {
+            let padded = tape.pad(x, &[(1, 0), (0, 2)]).unwrap();
+            let sliced = tape.slice(padded, &[(1, 3), (1, 4)]).unwrap();
+            let probabilities = tape.softmax(sliced, 1).unwrap();
+            let mean = tape.mean_axis(probabilities, 0).unwrap();
+            let centered = tape.sub(probabilities, mean).unwrap();
+            let squared = tape.powi(centered, 2);
+            let weighted = tape.mul(squared, x).unwrap();
+            let leaky = tape.leaky_relu(x, 0.1);
+            let joined = tape.concat(&[weighted, leaky], 0).unwrap();
+            tape.sum(joined)
+        };
+
+        let mut tape = Tape::new();
+        let x = tape.variable(input.clone());
+        let output = loss(&mut tape, x);
+        let gradients = tape.backward(output).unwrap();
+        let reference = tape.value(output).sum();
+
+        let epsilon = 1e-3;
+        for (index, grad) in gradients.get(x).unwrap().indexed_iter() {
+            let mut shifted = input.clone();
+            shifted[&index] += epsilon;
+            let mut shifted_tape = Tape::new();
+            let shifted_x = shifted_tape.variable(shifted);
+            let shifted_output = loss(&mut shifted_tape, shifted_x);
+            let numeric = (shifted_tape.value(shifted_output).sum() - reference) / epsilon;
+            assert_relative_eq!(*grad, numeric, epsilon = 1e-2);
+        }
+    }
+
     #[test]
     fn dense_plot() {
         let nn = DenseLayer::new(5, 5, Some(ActivationFunctionType::Relu));
@@ -104,9 +283,9 @@ mod tests {
 
         let mut nn = SequentialModel::new(3);
 
-        nn.push_layer("Input Layer".to_string(), layer0);
-        nn.push_layer("Hidden Layer 0".to_string(), layer1);
-        nn.push_layer("Hidden Layer 0".to_string(), layer2);
+        nn.push_layer("Input Layer".to_string(), layer0).unwrap();
+        nn.push_layer("Hidden Layer 0".to_string(), layer1).unwrap();
+        nn.push_layer("Hidden Layer 0".to_string(), layer2).unwrap();
 
         let x = array![[
             [-10.0],
@@ -135,6 +314,207 @@ mod tests {
         let _ = plot.save("plots/test_sequential.svg");
     }
 
+    #[test]
+    fn optimizers_reduce_loss() {
+        let inputs: Vec<_> = (0..8_u8).map(|x| array![[[f32::from(x) / 4.0]]]).collect();
+        let targets: Vec<_> = inputs.iter().map(|x| x * 3.0 - 1.0).collect();
+
+        for mut optimizer in [
+            Optimizer::sgd(0.05),
+            Optimizer::momentum(0.01),
+            Optimizer::adam(0.1),
+            Optimizer::adam_w(0.1),
+            Optimizer::rms_prop(0.05),
+            Optimizer::adagrad(0.5),
+        ] {
+            let mut nn = SequentialModel::new(1);
+            nn.push_layer(
+                "Dense".to_string(),
+                Layer::Dense(DenseLayer::new(1, 1, None)),
+            )
+            .unwrap();
+
+            let mut losses = Vec::new();
+            for _ in 0..50 {
+                nn.zero_gradients();
+                let outputs = nn.forward_train(&inputs).unwrap();
+                let mut loss = 0.0;
+                let mut grads = Vec::new();
+                for (output, target) in outputs.iter().zip(&targets) {
+                    let (sample_loss, grad) = mean_squared_error(output, target).unwrap();
+                    loss += sample_loss;
+                    grads.push(grad);
+                }
+                losses.push(loss);
+                nn.backward(&grads).unwrap();
+                optimizer.step(&mut nn).unwrap();
+            }
+            assert_lt!(losses[losses.len() - 1], losses[0] * 0.5);
+        }
+    }
+
+    #[test]
+    fn learning_rate_schedulers() {
+        let mut empty_model = SequentialModel::new(0);
+
+        let mut optimizer =
+            Optimizer::sgd(0.1).with_scheduler(LearningRateScheduler::step_decay(2, 0.5));
+        let mut learning_rates = Vec::new();
+        for _ in 0..4 {
+            learning_rates.push(optimizer.learning_rate);
+            optimizer.end_epoch(None);
+        }
+        assert_eq!(learning_rates, [0.1, 0.1, 0.05, 0.05]);
+
+        let mut optimizer =
+            Optimizer::sgd(1.0).with_scheduler(LearningRateScheduler::cosine_annealing(4, 14));
+        let mut learning_rates = Vec::new();
+        for _ in 0..15 {
+            learning_rates.push(optimizer.learning_rate);
+            optimizer.step(&mut empty_model).unwrap();
+        }
+        assert_relative_eq!(learning_rates[0], 0.25);
+        assert_relative_eq!(learning_rates[4], 1.0);
+        assert_relative_eq!(learning_rates[9], 0.5, epsilon = 1e-6);
+        assert_relative_eq!(learning_rates[14], 0.0, epsilon = 1e-6);
+
+        let mut optimizer =
+            Optimizer::sgd(0.1).with_scheduler(LearningRateScheduler::reduce_on_plateau(0.5, 2));
+        for loss in [1.0, 0.9, 0.95, 0.92] {
+            optimizer.end_epoch(Some(loss));
+        }
+        assert_relative_eq!(optimizer.learning_rate, 0.05);
+    }
+
+    #[test]
+    fn sequential_fit() {
+        let x: Vec<_> = (0..40_u8)
+            .map(|i| array![[[f32::from(i) / 20.0 - 1.0]]])
+            .collect();
+        let y: Vec<_> = x.iter().map(|x| x.map(|x| x * x)).collect();
+
+        let mut nn = SequentialModel::new(2);
+        nn.push_layer(
+            "Hidden".to_string(),
+            Layer::Dense(DenseLayer::new(1, 16, Some(ActivationFunctionType::Tanh))),
+        )
+        .unwrap();
+        nn.push_layer(
+            "Output".to_string(),
+            Layer::Dense(DenseLayer::new(16, 1, None)),
+        )
+        .unwrap();
+
+        let mut optimizer = Optimizer::adam(0.05);
+        let history = nn
+            .fit(
+                &x,
+                &y,
+                LossFunctionType::MeanSquaredError,
+                &mut optimizer,
+                60,
+                8,
+                0.2,
+            )
+            .unwrap();
+
+        assert_eq!(history.epochs(), 60);
+        assert_eq!(history.validation_loss.len(), 60);
+        assert_lt!(history.loss[59], history.loss[0]);
+        assert!(nn
+            .fit(
+                &x,
+                &y[1..],
+                LossFunctionType::MeanSquaredError,
+                &mut optimizer,
+                1,
+                8,
+                0.0
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn fit_callbacks() {
+        let x: Vec<_> = (0..10_u8).map(|i| array![[[f32::from(i)]]]).collect();
+        let y = x.clone();
+        let mut nn = SequentialModel::new(1);
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(1, 1, None)),
+        )
+        .unwrap();
+        let initial_weights = nn.weights();
+
+        let directory = std::env::temp_dir().join("carnaval_rust_fit_callbacks");
+        std::fs::create_dir_all(&directory).unwrap();
+        let csv_path = directory.join("log.csv");
+        let checkpoint_path = directory.join("epoch_{epoch}.weights");
+
+        // without learning the loss only changes by the rounding of the
+        // shuffled summation order, which min_delta keeps from counting as an
+        // improvement
+        let mut early_stopping = EarlyStopping::new(2, Some(1e-3), true);
+        let mut csv_logger = CsvLogger::new(csv_path.clone());
+        let mut checkpoint =
+            ModelCheckpoint::new(checkpoint_path.to_string_lossy().to_string(), None, false);
+        let history = nn
+            .fit_with_callbacks(
+                &x,
+                &y,
+                LossFunctionType::MeanSquaredError,
+                &mut Optimizer::sgd(0.0),
+                10,
+                4,
+                0.0,
+                &mut [&mut early_stopping, &mut csv_logger, &mut checkpoint],
+            )
+            .unwrap();
+
+        assert_eq!(history.epochs(), 3);
+        assert_eq!(early_stopping.stopped_epoch(), Some(2));
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(csv.starts_with("epoch,loss,validation_loss,learning_rate"));
+
+        nn.set_weights(&[array![[[5.0]]], array![[[5.0]]]]).unwrap();
+        nn.load_weights(&checkpoint.path_for_epoch(2)).unwrap();
+        assert_eq!(nn.weights(), initial_weights);
+        assert!(nn.set_weights(&[array![[[5.0]]]]).is_err());
+    }
+
+    #[test]
+    fn corrupted_weights_file() {
+        let mut nn = SequentialModel::new(1);
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(2, 3, None)),
+        )
+        .unwrap();
+        let directory = std::env::temp_dir().join("carnaval_rust_corrupted_weights");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("model.weights");
+        nn.save_weights(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        nn.load_weights(&path).unwrap();
+
+        // tensor count, then the dimensions of the first tensor
+        let corruptions: [(usize, u64); 4] =
+            [(4, u64::MAX), (12, u64::MAX), (12, 1 << 40), (28, 1 << 62)];
+        for (offset, value) in corruptions {
+            let mut corrupted = bytes.clone();
+            corrupted[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            std::fs::write(&path, &corrupted).unwrap();
+            let error = nn.load_weights(&path).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<WeightsError>(),
+                Some(WeightsError::InvalidFileError)
+            ));
+        }
+        std::fs::write(&path, &bytes[..bytes.len() - 4]).unwrap();
+        assert!(nn.load_weights(&path).is_err());
+    }
+
     #[test]
     fn conv2d_basic_test() {
         let input = array![
@@ -162,6 +542,163 @@ mod tests {
         println!("{result:?}");
     }
 
+    #[test]
+    fn conv2d_backward() {
+        let input = Array::random((4, 6, 2), Uniform::new(-1.0, 1.0));
+        let mut nn = Conv2dLayer::new(
+            3,
+            3,
+            (4, 6, 2),
+            Some((1, 1)),
+            Some((2, 2)),
+            None,
+            Some(ActivationFunctionType::Tanh),
+        )
+        .unwrap();
+
+        let outputs = nn.forward_train(std::slice::from_ref(&input)).unwrap();
+        assert_eq!(outputs[0].shape(), [2, 3, 3]);
+        let grad_inputs = nn.backward(&[Array::ones((2, 3, 3))]).unwrap();
+        assert_eq!(grad_inputs[0].shape(), input.shape());
+
+        let epsilon = 1e-2;
+        for (index, grad) in grad_inputs[0].indexed_iter() {
+            let mut shifted = input.clone();
+            shifted[index] += epsilon;
+            let numeric = (nn.forward(&shifted).unwrap().sum() - outputs[0].sum()) / epsilon;
+            assert_relative_eq!(*grad, numeric, epsilon = 2e-2);
+        }
+
+        // a 1x1 kernel without activation sees every input cell once
+        let mut pointwise = Conv2dLayer::new(1, 1, (4, 6, 2), None, None, None, None).unwrap();
+        pointwise
+            .forward_train(std::slice::from_ref(&input))
+            .unwrap();
+        pointwise.backward(&[Array::ones((4, 6, 1))]).unwrap();
+        for channel in 0..2 {
+            assert_relative_eq!(
+                pointwise.kernels_gradient()[0][[0, 0, channel]],
+                input.index_axis(Axis(2), channel).sum(),
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn gradient_check_every_layer() {
+        // max pooling has kinks where two cells of a window are equal, so
+        // every cell is kept far from the others
+        let image: Vec<_> = (0..2)
+            .map(|_| {
+                let mut values: Vec<f32> = (0..72_u8).map(|i| f32::from(i) / 36.0 - 1.0).collect();
+                values.shuffle(&mut rand::thread_rng());
+                Array::from_shape_vec((6, 6, 2), values).unwrap()
+            })
+            .collect();
+        let vector: Vec<_> = (0..2)
+            .map(|_| Array::random((1, 4, 1), Uniform::new(-1.0, 1.0)))
+            .collect();
+
+        let mut cases = vec![
+            (
+                Layer::Dense(DenseLayer::new(4, 3, Some(ActivationFunctionType::Tanh))),
+                &vector,
+            ),
+            (
+                Layer::Dense(DenseLayer::new(4, 3, Some(ActivationFunctionType::Softmax))),
+                &vector,
+            ),
+            (
+                Layer::Conv2d(
+                    Conv2dLayer::new(
+                        2,
+                        3,
+                        (6, 6, 2),
+                        Some((1, 1)),
+                        None,
+                        Some((2, 2)),
+                        Some(ActivationFunctionType::Sigmoid),
+                    )
+                    .unwrap(),
+                ),
+                &image,
+            ),
+            (
+                Layer::MaxPool2d(MaxPool2dLayer::new((2, 2), None, None)),
+                &image,
+            ),
+            (
+                Layer::AvgPool2d(AvgPool2dLayer::new((3, 3), Some((2, 2)), Some((1, 1)))),
+                &image,
+            ),
+            (
+                Layer::GlobalAveragePooling2d(GlobalAveragePooling2dLayer::new()),
+                &image,
+            ),
+            (
+                Layer::GlobalMaxPooling2d(GlobalMaxPooling2dLayer::new()),
+                &image,
+            ),
+            (Layer::Flatten(FlattenLayer::new()), &image),
+        ];
+
+        for (layer, inputs) in &mut cases {
+            let report = check_gradients(layer, inputs, None).unwrap();
+            assert_eq!(
+                report.tensors.len(),
+                inputs.len() + layer.parameters().len()
+            );
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn grouped_convolutions() {
+        let grouped = Conv2dLayer::new(2, 3, (4, 4, 4), None, None, None, None)
+            .unwrap()
+            .with_groups(2)
+            .unwrap();
+        assert_eq!(grouped.kernels()[0].dim(), (3, 3, 2));
+        // the first filter only reads the first two channels
+        let image = Array::random((4, 4, 4), Uniform::new(-1.0, 1.0));
+        let mut changed = image.clone();
+        changed.slice_mut(s![.., .., 2..]).fill(0.0);
+        let difference = grouped.forward(&image).unwrap() - grouped.forward(&changed).unwrap();
+        assert!(difference
+            .slice(s![.., .., 0])
+            .iter()
+            .all(|x| x.abs() < f32::EPSILON));
+        assert!(Conv2dLayer::new(2, 3, (4, 4, 4), None, None, None, None)
+            .unwrap()
+            .with_groups(3)
+            .is_err());
+
+        let depthwise =
+            DepthwiseConv2dLayer::new(3, (4, 4, 4), Some(2), Some((1, 1)), None, None, None)
+                .unwrap();
+        assert_eq!(depthwise.output_dim(), (4, 4, 8));
+        assert_eq!(depthwise.parameters().len(), 8);
+        let separable =
+            SeparableConv2dLayer::new(6, 3, (4, 4, 4), None, None, None, None, None).unwrap();
+        assert_eq!(separable.output_dim(), (2, 2, 6));
+        // 4 depthwise 3x3 kernels and 6 pointwise 1x1x4 ones
+        let weights: usize = separable.parameters().iter().map(|p| p.len()).sum();
+        assert_eq!(weights, 4 * 9 + 6 * 4);
+
+        let images: Vec<_> = (0..2)
+            .map(|_| Array::random((4, 4, 4), Uniform::new(-1.0, 1.0)))
+            .collect();
+        let mut cases = [
+            Layer::Conv2d(grouped),
+            Layer::DepthwiseConv2d(depthwise),
+            Layer::SeparableConv2d(separable),
+        ];
+        for layer in &mut cases {
+            let report = check_gradients(layer, &images, None).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
     #[test]
     fn maxpool2d_basic_test() {
         let input = array![
@@ -180,6 +717,1218 @@ mod tests {
         );
     }
 
+    #[test]
+    fn maxpool2d_backward() {
+        let input = array![
+            [[0.], [1.], [2.], [3.], [4.], [5.]],
+            [[6.], [7.], [8.], [9.], [10.], [11.]],
+            [[12.], [13.], [14.], [15.], [16.], [17.]],
+            [[18.], [19.], [20.], [21.], [22.], [23.]],
+        ];
+
+        let mut nn = MaxPool2dLayer::new((2, 2), None, None);
+        nn.forward_train(&[input]).unwrap();
+        let grad_inputs = nn
+            .backward(&[array![[[1.0], [2.0], [3.]], [[4.0], [5.0], [6.]]]])
+            .unwrap();
+
+        let mut expected = Array::zeros((4, 6, 1));
+        expected[[1, 1, 0]] = 1.0;
+        expected[[1, 3, 0]] = 2.0;
+        expected[[1, 5, 0]] = 3.0;
+        expected[[3, 1, 0]] = 4.0;
+        expected[[3, 3, 0]] = 5.0;
+        expected[[3, 5, 0]] = 6.0;
+        assert_eq!(grad_inputs[0], expected);
+    }
+
+    #[test]
+    fn maxpool2d_padding() {
+        // every input is negative, so padding cells must never win a window
+        let input =
+            Array::from_shape_vec((4, 4, 1), (1..=16_u8).map(|i| -f32::from(i)).collect()).unwrap();
+
+        let mut nn = MaxPool2dLayer::new((2, 2), None, Some((1, 1)));
+        let outputs = nn.forward_train(std::slice::from_ref(&input)).unwrap();
+        assert_eq!(
+            outputs[0],
+            array![
+                [[-1.], [-2.], [-4.]],
+                [[-5.], [-6.], [-8.]],
+                [[-13.], [-14.], [-16.]]
+            ]
+        );
+        assert_eq!(outputs[0], nn.forward(&input).unwrap());
+
+        let grad_output =
+            Array::from_shape_vec((3, 3, 1), (1..=9_u8).map(f32::from).collect()).unwrap();
+        let grad_inputs = nn.backward(&[grad_output]).unwrap();
+        // the gradients of the border windows only reach their input cells
+        let expected = array![
+            [[1.], [2.], [0.], [3.]],
+            [[4.], [5.], [0.], [6.]],
+            [[0.], [0.], [0.], [0.]],
+            [[7.], [8.], [0.], [9.]]
+        ];
+        assert_eq!(grad_inputs[0], expected);
+    }
+
+    #[test]
+    fn pooling_strides_and_global() {
+        let input = Array::from_shape_vec((4, 6, 1), (0..24_u8).map(f32::from).collect()).unwrap();
+
+        let max_pool = MaxPool2dLayer::new((2, 2), Some((2, 1)), None);
+        assert_eq!(max_pool.output_dim((4, 6, 1)), (2, 5, 1));
+        let result = max_pool.forward(&input).unwrap();
+        assert_eq!(
+            result.slice(s![.., .., 0]),
+            array![[7., 8., 9., 10., 11.], [19., 20., 21., 22., 23.]]
+        );
+
+        let avg_pool = AvgPool2dLayer::new((2, 2), None, Some((1, 1)));
+        assert_eq!(avg_pool.output_dim((4, 6, 1)), (3, 4, 1));
+        let result = avg_pool.forward(&input).unwrap();
+        // the top left window only sees one input cell, the others are padding
+        assert_relative_eq!(result[[0, 0, 0]], 0.0);
+        assert_relative_eq!(result[[1, 1, 0]], (7. + 8. + 13. + 14.) / 4.);
+
+        let image =
+            Array::from_shape_vec((2, 2, 2), vec![1., -1., 2., -2., 3., -3., 4., -8.]).unwrap();
+        let result = GlobalAveragePooling2dLayer::new().forward(&image).unwrap();
+        assert_eq!(result, array![[[2.5], [-3.5]]]);
+        let result = GlobalMaxPooling2dLayer::new().forward(&image).unwrap();
+        assert_eq!(result, array![[[4.], [-1.]]]);
+    }
+
+    #[test]
+    fn sequence_layers() {
+        let signal = Array::random((10, 1, 2), Uniform::new(-1.0, 1.0));
+        let causal =
+            Conv1dLayer::new(3, 3, 2, Some(Padding1d::Causal), None, Some(2), None).unwrap();
+        assert_eq!(causal.output_dim(signal.dim()), (10, 1, 3));
+        // changing the last step only changes the last output
+        let mut changed = signal.clone();
+        changed[[9, 0, 1]] += 1.0;
+        let difference = causal.forward(&changed).unwrap() - causal.forward(&signal).unwrap();
+        assert!(difference
+            .slice(s![..9, .., ..])
+            .iter()
+            .all(|x| x.abs() < f32::EPSILON));
+
+        let strided =
+            Conv1dLayer::new(4, 2, 2, Some(Padding1d::Same), Some(3), None, None).unwrap();
+        assert_eq!(strided.output_dim((10, 1, 2)), (4, 1, 4));
+        assert_eq!(
+            MaxPool1dLayer::new(3, None, None).output_dim((10, 1, 2)),
+            (3, 1, 2)
+        );
+        assert!(causal.forward(&Array::zeros((10, 2, 2))).is_err());
+
+        // softmax normalizes the filters of every step on its own
+        let softmax_conv = Conv1dLayer::new(
+            3,
+            2,
+            2,
+            None,
+            None,
+            None,
+            Some(ActivationFunctionType::Softmax),
+        )
+        .unwrap();
+        let output = softmax_conv.forward(&signal).unwrap();
+        for step in output.axis_iter(Axis(0)) {
+            assert_relative_eq!(step.sum(), 1.0, epsilon = 1e-5);
+        }
+
+        let steps = array![[[1.0]], [[3.0]], [[2.0]], [[5.0]], [[4.0]]];
+        let max_pool = MaxPool1dLayer::new(2, Some(1), Some(Padding1d::Causal));
+        assert_eq!(
+            max_pool.forward(&steps).unwrap(),
+            array![[[1.0]], [[3.0]], [[3.0]], [[5.0]], [[5.0]]]
+        );
+        // the padding step is left out of the last average
+        let avg_pool = AvgPool1dLayer::new(2, Some(2), Some(Padding1d::Same));
+        assert_eq!(
+            avg_pool.forward(&steps).unwrap(),
+            array![[[2.0]], [[3.5]], [[4.0]]]
+        );
+
+        let signals: Vec<_> = (0..2)
+            .map(|_| {
+                let mut values: Vec<f32> = (0..24_u8).map(|i| f32::from(i) / 12.0 - 1.0).collect();
+                values.shuffle(&mut rand::thread_rng());
+                Array::from_shape_vec((12, 1, 2), values).unwrap()
+            })
+            .collect();
+        let mut cases = [
+            Layer::Conv1d(causal),
+            Layer::Conv1d(
+                Conv1dLayer::new(
+                    2,
+                    3,
+                    2,
+                    Some(Padding1d::Same),
+                    Some(2),
+                    None,
+                    Some(ActivationFunctionType::Tanh),
+                )
+                .unwrap(),
+            ),
+            Layer::Conv1d(softmax_conv),
+            Layer::MaxPool1d(MaxPool1dLayer::new(3, Some(2), Some(Padding1d::Same))),
+            Layer::AvgPool1d(AvgPool1dLayer::new(3, Some(2), Some(Padding1d::Causal))),
+        ];
+        for layer in &mut cases {
+            let report = check_gradients(layer, &signals, None).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn upsampling_layers() {
+        let mut conv_transpose = Layer::Conv2dTranspose(
+            Conv2dTransposeLayer::new(1, 3, 1, Some((2, 2)), None, None).unwrap(),
+        );
+        conv_transpose.parameters_mut()[0].value.fill(1.0);
+        let output = conv_transpose
+            .forward(&Array::ones((2, 2, 1)), Mode::Inference)
+            .unwrap();
+        assert_eq!(output.dim(), (5, 5, 1));
+        // windows of neighbouring cells overlap on the middle row and column
+        assert_relative_eq!(output[[0, 0, 0]], 1.0);
+        assert_relative_eq!(output[[0, 2, 0]], 2.0);
+        assert_relative_eq!(output[[2, 2, 0]], 4.0);
+
+        let nearest = UpSampling2dLayer::new((2, 1), None).unwrap();
+        assert_eq!(
+            nearest.forward(&array![[[1.0], [2.0]]]).unwrap(),
+            array![[[1.0], [2.0]], [[1.0], [2.0]]]
+        );
+        let bilinear = UpSampling2dLayer::new((1, 2), Some(Interpolation::Bilinear)).unwrap();
+        let output = bilinear.forward(&array![[[0.0], [1.0]]]).unwrap();
+        for (value, expected) in output.iter().zip([0.0, 0.25, 0.75, 1.0]) {
+            assert_relative_eq!(*value, expected);
+        }
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let images: Vec<_> = (0..2)
+            .map(|_| Array::random_using((3, 4, 2), Uniform::new(-1.0, 1.0), &mut rng))
+            .collect();
+        // softmax normalizes the filters of every output cell on its own
+        let softmax_conv = Conv2dTransposeLayer::new(
+            3,
+            2,
+            2,
+            Some((2, 2)),
+            None,
+            Some(ActivationFunctionType::Softmax),
+        )
+        .unwrap();
+        let output = softmax_conv.forward(&images[0]).unwrap();
+        assert_eq!(output.dim(), (6, 8, 3));
+        for cell in output.rows() {
+            assert_relative_eq!(cell.sum(), 1.0, epsilon = 1e-5);
+        }
+
+        let mut cases = [
+            Layer::Conv2dTranspose(softmax_conv),
+            Layer::Conv2dTranspose(
+                Conv2dTransposeLayer::new(
+                    2,
+                    3,
+                    2,
+                    Some((2, 1)),
+                    Some((1, 0)),
+                    Some(ActivationFunctionType::Sigmoid),
+                )
+                .unwrap(),
+            ),
+            Layer::UpSampling2d(UpSampling2dLayer::new((2, 3), None).unwrap()),
+            Layer::UpSampling2d(
+                UpSampling2dLayer::new((3, 2), Some(Interpolation::Bilinear)).unwrap(),
+            ),
+        ];
+        for layer in &mut cases {
+            for parameter in layer.parameters_mut() {
+                *parameter.value = Array::random_using(
+                    parameter.value.raw_dim(),
+                    Uniform::new(-1.0, 1.0),
+                    &mut rng,
+                );
+            }
+            let report = check_gradients_using(layer, &images, None, &mut rng).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn recurrent_layers() {
+        let sequence = Array::random((6, 1, 3), Uniform::new(-1.0, 1.0));
+        let simple_rnn = SimpleRnnLayer::new(3, 4, true, None);
+        assert_eq!(simple_rnn.forward(&sequence).unwrap().dim(), (6, 1, 4));
+        let gru = GruLayer::new(3, 4, false, None, None);
+        assert_eq!(gru.forward(&sequence).unwrap().dim(), (1, 4, 1));
+
+        // resuming from the states of the first half gives the same result as
+        // running the whole sequence
+        let mut lstm = LstmLayer::new(3, 4, false, None, None);
+        let whole = lstm.forward(&sequence).unwrap();
+        let first_half = sequence.slice(s![..3, .., ..]).to_owned();
+        let second_half = sequence.slice(s![3.., .., ..]).to_owned();
+        let (_, states) = lstm.forward_with_state(&first_half, &[]).unwrap();
+        assert_eq!(states.len(), 2);
+        let (resumed, _) = lstm.forward_with_state(&second_half, &states).unwrap();
+        for (a, b) in whole.iter().zip(&resumed) {
+            assert_relative_eq!(a, b, epsilon = 1e-5);
+        }
+        lstm.set_initial_state(states.clone()).unwrap();
+        let resumed = lstm.forward(&second_half).unwrap();
+        for (a, b) in whole.iter().zip(&resumed) {
+            assert_relative_eq!(a, b, epsilon = 1e-5);
+        }
+        assert!(lstm.set_initial_state(states[..1].to_vec()).is_err());
+        assert!(lstm.forward(&Array::zeros((6, 1, 2))).is_err());
+
+        let sequences: Vec<_> = (0..2)
+            .map(|_| Array::random((4, 1, 3), Uniform::new(-1.0, 1.0)))
+            .collect();
+        let mut cases = [
+            Layer::SimpleRnn(simple_rnn),
+            Layer::Lstm(lstm),
+            Layer::Lstm(LstmLayer::new(3, 2, true, None, None)),
+            Layer::Gru(gru),
+            Layer::Gru(GruLayer::new(
+                3,
+                2,
+                true,
+                Some(ActivationFunctionType::Sigmoid),
+                None,
+            )),
+        ];
+        for layer in &mut cases {
+            let report = check_gradients(layer, &sequences, None).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn attention_layers() {
+        // seeded, as the gradient check of random sequences and weights
+        // misses the tolerance in about one run out of twelve
+        let mut rng = StdRng::seed_from_u64(0);
+        let sequence = Array::random_using((5, 1, 4), Uniform::new(-1.0, 1.0), &mut rng);
+        let causal = MultiHeadAttentionLayer::new_using(4, 2, true, &mut rng).unwrap();
+        let output = causal.forward(&sequence).unwrap();
+        assert_eq!(output.dim(), (5, 1, 4));
+        // with a causal mask the last step cannot change earlier outputs
+        let mut changed = sequence.clone();
+        changed[[4, 0, 2]] += 1.0;
+        let difference = causal.forward(&changed).unwrap() - &output;
+        assert!(difference
+            .slice(s![..4, .., ..])
+            .iter()
+            .all(|x| x.abs() < 1e-6));
+        // an explicit lower triangular mask is the same as the causal flag
+        let mut unmasked = MultiHeadAttentionLayer::new(4, 2, false).unwrap();
+        for (parameter, value) in unmasked
+            .parameters_mut()
+            .into_iter()
+            .zip(causal.parameters())
+        {
+            parameter.value.assign(value);
+        }
+        let lower_triangular = Array::from_shape_fn((5, 5), |(query, key)| key <= query);
+        let masked = unmasked
+            .forward_with_mask(&sequence, &lower_triangular)
+            .unwrap();
+        for (a, b) in masked.iter().zip(&output) {
+            assert_relative_eq!(a, b, epsilon = 1e-5);
+        }
+        unmasked.set_mask(Some(Array::from_elem((4, 4), true)));
+        assert!(unmasked.forward(&sequence).is_err());
+        assert!(MultiHeadAttentionLayer::new(4, 3, false).is_err());
+
+        let mut block = Layer::TransformerEncoderBlock(
+            TransformerEncoderBlockLayer::new_using(4, 2, 8, false, &mut rng).unwrap(),
+        );
+        assert_eq!(block.parameters().len(), 8 + 2 + 2 + 2 + 2);
+        // keeps the feed-forward relu away from its kink
+        block.parameters_mut()[11].value.fill(20.0);
+        assert_eq!(
+            block.forward(&sequence, Mode::Inference).unwrap().dim(),
+            (5, 1, 4)
+        );
+
+        let sequences: Vec<_> = (0..2)
+            .map(|_| Array::random_using((3, 1, 4), Uniform::new(-1.0, 1.0), &mut rng))
+            .collect();
+        let mut cases = [Layer::MultiHeadAttention(causal), block];
+        for layer in &mut cases {
+            let report = check_gradients_using(layer, &sequences, None, &mut rng).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
+    #[test]
+    fn embedding_layer() {
+        let mut embedding = EmbeddingLayer::new(5, 3, Some(0)).unwrap();
+        let tokens = array![[[2.0], [0.0], [4.0], [2.0]]];
+        let output = embedding.forward(&tokens).unwrap();
+        assert_eq!(output.dim(), (4, 1, 3));
+        for size in 0..3 {
+            assert_relative_eq!(output[[0, 0, size]], embedding.embeddings[[2, size, 0]]);
+            assert_relative_eq!(output[[1, 0, size]], 0.0);
+        }
+        assert!(embedding.forward(&array![[[5.0]]]).is_err());
+        assert!(embedding.forward(&array![[[1.5]]]).is_err());
+        assert!(EmbeddingLayer::new(5, 3, Some(5)).is_err());
+
+        // repeated tokens add up and the padding vector is never trained
+        embedding
+            .forward_train(std::slice::from_ref(&tokens))
+            .unwrap();
+        let grad_inputs = embedding.backward(&[Array::ones((4, 1, 3))]).unwrap();
+        assert_eq!(grad_inputs[0].dim(), tokens.dim());
+        for size in 0..3 {
+            assert_relative_eq!(embedding.embeddings_gradient[[2, size, 0]], 2.0);
+            assert_relative_eq!(embedding.embeddings_gradient[[4, size, 0]], 1.0);
+            assert_relative_eq!(embedding.embeddings_gradient[[0, size, 0]], 0.0);
+        }
+
+        let mut nn = SequentialModel::new(3);
+        nn.push_layer("Embedding".to_string(), Layer::Embedding(embedding))
+            .unwrap();
+        nn.push_layer(
+            "LSTM".to_string(),
+            Layer::Lstm(LstmLayer::new(3, 4, false, None, None)),
+        )
+        .unwrap();
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(4, 2, Some(ActivationFunctionType::Softmax))),
+        )
+        .unwrap();
+        let x = vec![tokens, array![[[1.0]], [[3.0]]]];
+        let y = vec![array![[[1.0], [0.0]]], array![[[0.0], [1.0]]]];
+        let mut optimizer = Optimizer::adam(0.05);
+        let history = nn
+            .fit(
+                &x,
+                &y,
+                LossFunctionType::CategoricalCrossEntropy,
+                &mut optimizer,
+                30,
+                2,
+                0.0,
+            )
+            .unwrap();
+        assert_lt!(history.loss[29], history.loss[0]);
+    }
+
+    // Residual block and two branches concatenated, gated by a second input
+    fn functional_test_model() -> FunctionalModel {
+        let mut nn = FunctionalModel::new(9);
+        nn.add_input("x".to_string()).unwrap();
+        nn.add_input("gate".to_string()).unwrap();
+        nn.add_layer(
+            "hidden".to_string(),
+            Layer::Dense(DenseLayer::new(4, 4, None)),
+            "x",
+        )
+        .unwrap();
+        nn.add_merge("skip".to_string(), Merge::Add, &["x", "hidden"])
+            .unwrap();
+        nn.add_layer(
+            "branch".to_string(),
+            Layer::Dense(DenseLayer::new(4, 3, None)),
+            "x",
+        )
+        .unwrap();
+        nn.add_merge(
+            "concat".to_string(),
+            Merge::Concatenate { axis: 1 },
+            &["skip", "branch"],
+        )
+        .unwrap();
+        nn.add_merge("gated".to_string(), Merge::Multiply, &["concat", "gate"])
+            .unwrap();
+        nn.add_merge("mean".to_string(), Merge::Average, &["gated", "concat"])
+            .unwrap();
+        nn.add_layer(
+            "out".to_string(),
+            Layer::Dense(DenseLayer::new(7, 2, None)),
+            "mean",
+        )
+        .unwrap();
+        nn
+    }
+
+    #[test]
+    fn functional_model() {
+        let a = array![[[1.0], [2.0]]];
+        let b = array![[[3.0], [-1.0]]];
+        assert_eq!(
+            Merge::Add.forward(&[&a, &b]).unwrap(),
+            array![[[4.0], [1.0]]]
+        );
+        assert_eq!(
+            Merge::Multiply.forward(&[&a, &b]).unwrap(),
+            array![[[3.0], [-2.0]]]
+        );
+        assert_eq!(
+            Merge::Average.forward(&[&a, &b]).unwrap(),
+            array![[[2.0], [0.5]]]
+        );
+        assert_eq!(
+            Merge::Concatenate { axis: 1 }.forward(&[&a, &b]).unwrap(),
+            array![[[1.0], [2.0], [3.0], [-1.0]]]
+        );
+        assert!(Merge::Add.forward(&[&a, &array![[[1.0]]]]).is_err());
+
+        let mut nn = functional_test_model();
+        assert!(nn.predict(&[array![[[1.0]]]]).is_err());
+        nn.set_outputs(&["out", "skip"]).unwrap();
+
+        assert!(nn.add_input("x".to_string()).is_err());
+        assert!(nn
+            .add_layer(
+                "dense".to_string(),
+                Layer::Flatten(FlattenLayer::new()),
+                "y"
+            )
+            .is_err());
+        assert!(nn.add_merge("sum".to_string(), Merge::Add, &["x"]).is_err());
+        assert_eq!(nn.input_names(), vec!["x", "gate"]);
+        assert_eq!(nn.output_names(), vec!["out", "skip"]);
+
+        let x = Array::random((1, 4, 1), Uniform::new(-1.0, 1.0));
+        let gate = Array::random((1, 7, 1), Uniform::new(-1.0, 1.0));
+        let outputs = nn.predict(&[x.clone(), gate.clone()]).unwrap();
+        assert_eq!(outputs[0].dim(), (1, 2, 1));
+        assert_eq!(outputs[1].dim(), (1, 4, 1));
+        assert!(nn.predict(std::slice::from_ref(&x)).is_err());
+
+        // input gradients against central differences of
+        // sum(out * projection) + sum(skip * projection)
+        let projections = [
+            Array::random((1, 2, 1), Uniform::new(-1.0, 1.0)),
+            Array::random((1, 4, 1), Uniform::new(-1.0, 1.0)),
+        ];
+        let projected_loss = |nn: &FunctionalModel, x: &Array<f32, Ix3>, gate: &Array<f32, Ix3>| {
+            nn.predict(&[x.clone(), gate.clone()])
+                .unwrap()
+                .iter()
+                .zip(&projections)
+                .map(|(output, projection)| (output * projection).sum())
+                .sum::<f32>()
+        };
+        assert!(nn.backward(&[vec![], vec![]]).is_err());
+        nn.forward_train(&[vec![x.clone()], vec![gate.clone()]])
+            .unwrap();
+        let grads = nn
+            .backward(&[vec![projections[0].clone()], vec![projections[1].clone()]])
+            .unwrap();
+        let epsilon = 1e-2;
+        for (index, analytic) in grads[0][0].indexed_iter() {
+            let mut perturbed = x.clone();
+            perturbed[index] += epsilon;
+            let loss_plus = projected_loss(&nn, &perturbed, &gate);
+            perturbed[index] -= 2.0 * epsilon;
+            let loss_minus = projected_loss(&nn, &perturbed, &gate);
+            let numeric = (loss_plus - loss_minus) / (2.0 * epsilon);
+            assert_relative_eq!(*analytic, numeric, epsilon = 1e-2, max_relative = 1e-2);
+        }
+        for (index, analytic) in grads[1][0].indexed_iter() {
+            let mut perturbed = gate.clone();
+            perturbed[index] += epsilon;
+            let loss_plus = projected_loss(&nn, &x, &perturbed);
+            perturbed[index] -= 2.0 * epsilon;
+            let loss_minus = projected_loss(&nn, &x, &perturbed);
+            let numeric = (loss_plus - loss_minus) / (2.0 * epsilon);
+            assert_relative_eq!(*analytic, numeric, epsilon = 1e-2, max_relative = 1e-2);
+        }
+
+        // only the "out" output is trained here, "skip" gets no gradient
+        let target = array![[[0.5], [-0.5]]];
+        let mut optimizer = Optimizer::sgd(0.01);
+        let mut losses = Vec::new();
+        for _ in 0..20 {
+            nn.zero_gradients();
+            let outputs = nn
+                .forward_train(&[vec![x.clone()], vec![gate.clone()]])
+                .unwrap();
+            let (loss, grad) = LossFunctionType::MeanSquaredError
+                .compute(&outputs[0][0], &target)
+                .unwrap();
+            losses.push(loss);
+            nn.backward(&[vec![grad], vec![Array::zeros((1, 4, 1))]])
+                .unwrap();
+            optimizer.step_parameters(nn.parameters_mut()).unwrap();
+        }
+        assert_lt!(losses[19], losses[0]);
+    }
+
+    #[test]
+    fn residual_layer() {
+        // predict no longer adds the input of every layer to its output, so
+        // layers are free to change shapes and predict matches forward
+        let mut nn = SequentialModel::new(2);
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(2, 3, Some(ActivationFunctionType::Tanh))),
+        )
+        .unwrap();
+        nn.push_layer(
+            "Dropout".to_string(),
+            Layer::Dropout(DropoutLayer::new(0.5).unwrap()),
+        )
+        .unwrap();
+        let x = array![[[0.5], [-1.0]]];
+        let prediction = nn.predict(&x).unwrap();
+        assert_eq!(prediction.dim(), (1, 3, 1));
+        assert_eq!(prediction, nn.forward(&x).unwrap());
+
+        let dense = DenseLayer::new(2, 2, None);
+        let expected = dense.forward(&x).unwrap() + &x;
+        let residual =
+            Layer::Residual(ResidualLayer::new(vec![Layer::Dense(dense)], None).unwrap());
+        assert_eq!(residual.forward(&x, Mode::Inference).unwrap(), expected);
+        assert!(ResidualLayer::new(Vec::new(), None).is_err());
+
+        // the shortcut needs a projection when the layers change the shape
+        let widening =
+            ResidualLayer::new(vec![Layer::Dense(DenseLayer::new(2, 3, None))], None).unwrap();
+        assert!(widening.forward(&x, Mode::Inference).is_err());
+
+        // seeded, as the layer normalization of the three channels written by
+        // the convolution amplifies f32 rounding up to 1 / sqrt(epsilon) times
+        // at positions where those channels nearly coincide, which random
+        // inputs and kernels hit in about half of the runs
+        let mut rng = StdRng::seed_from_u64(0);
+        let images: Vec<_> = (0..2)
+            .map(|_| Array::random_using((4, 4, 2), Uniform::new(-1.0, 1.0), &mut rng))
+            .collect();
+        let conv = |filters, kernel_size, padding, activation, rng: &mut StdRng| {
+            let mut layer = Layer::Conv2d(
+                Conv2dLayer::new(
+                    filters,
+                    kernel_size,
+                    (4, 4, 2),
+                    padding,
+                    None,
+                    None,
+                    activation,
+                )
+                .unwrap(),
+            );
+            for parameter in layer.parameters_mut() {
+                *parameter.value =
+                    Array::random_using(parameter.value.raw_dim(), Uniform::new(-1.0, 1.0), rng);
+            }
+            layer
+        };
+        let tanh = Some(ActivationFunctionType::Tanh);
+        let mut cases = [
+            Layer::Residual(
+                ResidualLayer::new(vec![conv(2, 3, Some((1, 1)), tanh, &mut rng)], None).unwrap(),
+            ),
+            Layer::Residual(
+                ResidualLayer::new(
+                    vec![
+                        conv(3, 3, Some((1, 1)), tanh, &mut rng),
+                        Layer::LayerNorm(LayerNormLayer::for_channels(3, None)),
+                    ],
+                    Some(conv(3, 1, None, None, &mut rng)),
+                )
+                .unwrap(),
+            ),
+        ];
+        for layer in &mut cases {
+            let report = check_gradients_using(layer, &images, None, &mut rng).unwrap();
+            assert_eq!(
+                report.tensors.len(),
+                images.len() + layer.parameters().len()
+            );
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+        assert_eq!(cases[1].parameters().len(), 3 + 2 + 3);
+    }
+
+    // One image layer of every kind along with an input it can take
+    fn image_output_dim_cases() -> Vec<(Layer, Array<f32, Ix3>)> {
+        let image = Array::random((6, 6, 4), Uniform::new(-1.0, 1.0));
+        vec![
+            (
+                Layer::Conv2d(
+                    Conv2dLayer::new(2, 3, (6, 6, 4), None, Some((2, 2)), None, None).unwrap(),
+                ),
+                image.clone(),
+            ),
+            (
+                Layer::MaxPool2d(MaxPool2dLayer::new((2, 2), None, None)),
+                image.clone(),
+            ),
+            (Layer::Flatten(FlattenLayer::new()), image.clone()),
+            (
+                Layer::Dropout(DropoutLayer::new(0.5).unwrap()),
+                image.clone(),
+            ),
+            (
+                Layer::BatchNorm(BatchNormLayer::for_conv2d(4, None, None)),
+                image.clone(),
+            ),
+            (
+                Layer::GroupNorm(GroupNormLayer::new(2, 4, None).unwrap()),
+                image.clone(),
+            ),
+            (
+                Layer::AvgPool2d(AvgPool2dLayer::new((3, 3), Some((2, 2)), Some((1, 1)))),
+                image.clone(),
+            ),
+            (
+                Layer::GlobalAveragePooling2d(GlobalAveragePooling2dLayer::new()),
+                image.clone(),
+            ),
+            (
+                Layer::GlobalMaxPooling2d(GlobalMaxPooling2dLayer::new()),
+                image.clone(),
+            ),
+            (
+                Layer::Conv2dTranspose(
+                    Conv2dTransposeLayer::new(2, 3, 4, Some((2, 2)), Some((1, 1)), None).unwrap(),
+                ),
+                image.clone(),
+            ),
+            (
+                Layer::UpSampling2d(UpSampling2dLayer::new((2, 3), None).unwrap()),
+                image.clone(),
+            ),
+            (
+                Layer::DepthwiseConv2d(
+                    DepthwiseConv2dLayer::new(3, (6, 6, 4), Some(2), None, None, None, None)
+                        .unwrap(),
+                ),
+                image.clone(),
+            ),
+            (
+                Layer::SeparableConv2d(
+                    SeparableConv2dLayer::new(
+                        3,
+                        3,
+                        (6, 6, 4),
+                        None,
+                        Some((1, 1)),
+                        None,
+                        None,
+                        None,
+                    )
+                    .unwrap(),
+                ),
+                image.clone(),
+            ),
+        ]
+    }
+
+    // Same for the other layers, then the image ones
+    fn output_dim_cases() -> Vec<(Layer, Array<f32, Ix3>)> {
+        let sequence = Array::random((5, 1, 4), Uniform::new(-1.0, 1.0));
+        let vector = Array::random((1, 4, 1), Uniform::new(-1.0, 1.0));
+        let mut cases = vec![
+            (Layer::Dense(DenseLayer::new(4, 3, None)), vector.clone()),
+            (
+                Layer::BatchNorm(BatchNormLayer::for_dense(4, None, None)),
+                vector.clone(),
+            ),
+            (
+                Layer::LayerNorm(LayerNormLayer::for_dense(4, None)),
+                vector.clone(),
+            ),
+            (
+                Layer::Conv1d(
+                    Conv1dLayer::new(3, 3, 4, Some(Padding1d::Same), Some(2), None, None).unwrap(),
+                ),
+                sequence.clone(),
+            ),
+            (
+                Layer::MaxPool1d(MaxPool1dLayer::new(2, None, None)),
+                sequence.clone(),
+            ),
+            (
+                Layer::AvgPool1d(AvgPool1dLayer::new(3, Some(1), Some(Padding1d::Causal))),
+                sequence.clone(),
+            ),
+            (
+                Layer::SimpleRnn(SimpleRnnLayer::new(4, 3, true, None)),
+                sequence.clone(),
+            ),
+            (
+                Layer::Lstm(LstmLayer::new(4, 3, false, None, None)),
+                sequence.clone(),
+            ),
+            (
+                Layer::Gru(GruLayer::new(4, 3, true, None, None)),
+                sequence.clone(),
+            ),
+            (
+                Layer::MultiHeadAttention(MultiHeadAttentionLayer::new(4, 2, false).unwrap()),
+                sequence.clone(),
+            ),
+            (
+                Layer::TransformerEncoderBlock(
+                    TransformerEncoderBlockLayer::new(4, 2, 8, false).unwrap(),
+                ),
+                sequence.clone(),
+            ),
+            (
+                Layer::Embedding(EmbeddingLayer::new(5, 3, None).unwrap()),
+                array![[[1.0], [4.0]]],
+            ),
+            (
+                Layer::Residual(
+                    ResidualLayer::new(
+                        vec![Layer::Dense(DenseLayer::new(4, 2, None))],
+                        Some(Layer::Dense(DenseLayer::new(4, 2, None))),
+                    )
+                    .unwrap(),
+                ),
+                vector.clone(),
+            ),
+        ];
+        cases.extend(image_output_dim_cases());
+        cases
+    }
+
+    #[test]
+    fn layer_output_dims() {
+        // the inferred shape of every layer matches the one of its output
+        for (layer, input) in &output_dim_cases() {
+            let output = layer.forward(input, Mode::Inference).unwrap();
+            assert_eq!(layer.output_dim(input.dim()), Some(output.dim()));
+        }
+
+        let dense = Layer::Dense(DenseLayer::new(4, 3, None));
+        assert_eq!(dense.output_dim((1, 5, 1)), None);
+        assert_eq!(dense.output_dim((2, 4, 1)), None);
+        let pool = Layer::MaxPool2d(MaxPool2dLayer::new((3, 3), None, None));
+        assert_eq!(pool.output_dim((2, 2, 1)), None);
+        let residual = Layer::Residual(
+            ResidualLayer::new(vec![Layer::Dense(DenseLayer::new(4, 2, None))], None).unwrap(),
+        );
+        assert_eq!(residual.output_dim((1, 4, 1)), None);
+    }
+
+    #[test]
+    fn shape_inference() {
+        let mut nn = SequentialModel::with_input_dim((8, 8, 3), 5);
+        nn.push_layer(
+            "Conv2D".to_string(),
+            Layer::Conv2d(Conv2dLayer::new(4, 3, (8, 8, 3), None, None, None, None).unwrap()),
+        )
+        .unwrap();
+        nn.push_layer(
+            "MaxPool2D".to_string(),
+            Layer::MaxPool2d(MaxPool2dLayer::new((2, 2), None, None)),
+        )
+        .unwrap();
+        nn.push_layer(
+            "GlobalAveragePooling2D".to_string(),
+            Layer::GlobalAveragePooling2d(GlobalAveragePooling2dLayer::new()),
+        )
+        .unwrap();
+        assert_eq!(nn.output_dims(), &[(6, 6, 4), (3, 3, 4), (1, 4, 1)]);
+
+        // the error names the layer that does not fit and the model is kept
+        // as it was
+        let error = nn
+            .push_layer(
+                "Classifier".to_string(),
+                Layer::Dense(DenseLayer::new(16, 2, None)),
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "layer Classifier cannot take an input of shape (1, 4, 1)"
+        );
+        nn.push_layer(
+            "Classifier".to_string(),
+            Layer::Dense(DenseLayer::new(4, 2, None)),
+        )
+        .unwrap();
+        assert_eq!(nn.input_dim(), Some((8, 8, 3)));
+        assert_eq!(nn.output_dim(), Some((1, 2, 1)));
+
+        let input = Array::random((8, 8, 3), Uniform::new(-1.0, 1.0));
+        assert_eq!(nn.predict(&input).unwrap().dim(), (1, 2, 1));
+        assert!(nn.predict(&Array::zeros((9, 9, 3))).is_err());
+
+        // without an input shape, layers are pushed unchecked
+        let mut unchecked = SequentialModel::new(1);
+        unchecked
+            .push_layer(
+                "Dense".to_string(),
+                Layer::Dense(DenseLayer::new(16, 2, None)),
+            )
+            .unwrap();
+        assert_eq!(unchecked.output_dim(), None);
+    }
+
+    #[test]
+    fn model_summary() {
+        let mut nn = SequentialModel::with_input_dim((6, 6, 1), 4);
+        nn.push_layer(
+            "Conv".to_string(),
+            Layer::Conv2d(Conv2dLayer::new(2, 3, (6, 6, 1), None, None, None, None).unwrap()),
+        )
+        .unwrap();
+        nn.push_layer(
+            "Pool".to_string(),
+            Layer::MaxPool2d(MaxPool2dLayer::new((2, 2), None, None)),
+        )
+        .unwrap();
+        nn.push_layer("Flatten".to_string(), Layer::Flatten(FlattenLayer::new()))
+            .unwrap();
+        nn.push_layer(
+            "Output".to_string(),
+            Layer::Dense(DenseLayer::new(8, 300, None)),
+        )
+        .unwrap();
+
+        let expected = [
+            "Layer    Type       Output shape  Parameters  Activation memory",
+            "===============================================================",
+            "Conv     Conv2d     (4, 4, 2)             18              128 B",
+            "Pool     MaxPool2d  (2, 2, 2)              0               32 B",
+            "Flatten  Flatten    (1, 8, 1)              0               32 B",
+            "Output   Dense      (1, 300, 1)         2700            1.2 KiB",
+            "===============================================================",
+            "Total parameters: 2718",
+            "Parameter memory: 10.6 KiB",
+            "Activation memory per sample: 1.4 KiB",
+        ];
+        assert_eq!(nn.summary(), expected.join("\n") + "\n");
+
+        // shapes are unknown without the input shape
+        let mut unshaped = SequentialModel::new(1);
+        unshaped
+            .push_layer(
+                "Dense".to_string(),
+                Layer::Dense(DenseLayer::new(2, 3, None)),
+            )
+            .unwrap();
+        let summary = unshaped.summary();
+        assert!(summary.contains("Dense  Dense  ?                      9                  ?"));
+        assert!(summary.ends_with("Activation memory per sample: ?\n"));
+    }
+
+    // Learnable scalar multiplying its input, as a downstream crate would
+    // write it
+    struct ScaleLayer {
+        scale: Array<f32, Ix3>,
+        scale_gradient: Array<f32, Ix3>,
+        inputs: Vec<Array<f32, Ix3>>,
+    }
+
+    impl CustomLayer for ScaleLayer {
+        fn type_name(&self) -> &'static str {
+            "Scale"
+        }
+
+        fn output_dim(&self, input_dim: (usize, usize, usize)) -> Option<(usize, usize, usize)> {
+            Some(input_dim)
+        }
+
+        fn forward(
+            &self,
+            input: &Array<f32, Ix3>,
+            _mode: Mode,
+        ) -> Result<Array<f32, Ix3>, Box<dyn std::error::Error>> {
+            Ok(input * self.scale[[0, 0, 0]])
+        }
+
+        fn forward_train(
+            &mut self,
+            inputs: &[Array<f32, Ix3>],
+        ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn std::error::Error>> {
+            self.inputs = inputs.to_vec();
+            inputs
+                .iter()
+                .map(|input| self.forward(input, Mode::Training))
+                .collect()
+        }
+
+        fn backward(
+            &mut self,
+            grad_outputs: &[Array<f32, Ix3>],
+        ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn std::error::Error>> {
+            if grad_outputs.len() != self.inputs.len() {
+                return Err("backward needs a forward_train call with the same batch size".into());
+            }
+            for (input, grad_output) in self.inputs.iter().zip(grad_outputs) {
+                self.scale_gradient[[0, 0, 0]] += (input * grad_output).sum();
+            }
+            Ok(grad_outputs
+                .iter()
+                .map(|grad_output| grad_output * self.scale[[0, 0, 0]])
+                .collect())
+        }
+
+        fn zero_gradients(&mut self) {
+            self.scale_gradient.fill(0.0);
+        }
+
+        fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+            vec![&self.scale]
+        }
+
+        fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+            vec![Parameter {
+                value: &mut self.scale,
+                gradient: &self.scale_gradient,
+            }]
+        }
+    }
+
+    fn scale_layer(scale: f32) -> Layer {
+        Layer::Custom(Box::new(ScaleLayer {
+            scale: Array::from_elem((1, 1, 1), scale),
+            scale_gradient: Array::zeros((1, 1, 1)),
+            inputs: Vec::new(),
+        }))
+    }
+
+    #[test]
+    fn custom_layer() {
+        let mut layer = scale_layer(1.5);
+        assert_eq!(
+            layer
+                .forward(&array![[[2.0], [-1.0]]], Mode::Inference)
+                .unwrap(),
+            array![[[3.0], [-1.5]]]
+        );
+        assert_eq!(layer.type_name(), "Scale");
+        assert!(layer.backward(&[array![[[1.0]]]]).is_err());
+        let inputs = vec![Array::random((1, 3, 1), Uniform::new(-1.0, 1.0)); 2];
+        let report = check_gradients(&mut layer, &inputs, None).unwrap();
+        assert!(report.passes(1e-2), "{report:?}");
+
+        // trained and saved along with the built-in layers
+        let mut nn = SequentialModel::with_input_dim((1, 1, 1), 2);
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(1, 3, None)),
+        )
+        .unwrap();
+        nn.push_layer("Scale".to_string(), scale_layer(0.1))
+            .unwrap();
+        assert!(nn.summary().contains("Scale  Scale  (1, 3, 1)"));
+        assert_eq!(nn.weights().len(), 3);
+
+        let x: Vec<_> = (0..8_u8).map(|x| array![[[f32::from(x) / 4.0]]]).collect();
+        let y: Vec<_> = x
+            .iter()
+            .map(|x| Array::from_elem((1, 3, 1), x[[0, 0, 0]] * 5.0))
+            .collect();
+        let mut optimizer = Optimizer::adam(0.05);
+        let history = nn
+            .fit(
+                &x,
+                &y,
+                LossFunctionType::MeanSquaredError,
+                &mut optimizer,
+                40,
+                4,
+                0.0,
+            )
+            .unwrap();
+        assert_lt!(history.loss[39], history.loss[0]);
+    }
+
+    #[test]
+    fn sequential_backward() {
+        let mut values: Vec<f32> = (0..36_u8).map(|i| f32::from(i) / 18.0 - 1.0).collect();
+        values.shuffle(&mut rand::thread_rng());
+        let input = Array::from_shape_vec((6, 6, 1), values).unwrap();
+        let conv = Conv2dLayer::new(2, 1, (6, 6, 1), None, None, None, None).unwrap();
+        let max_pool = MaxPool2dLayer::new((2, 2), None, None);
+        let dense_input_size = {
+            let (height, width, channels) = max_pool.output_dim(conv.output_dim);
+            height * width * channels
+        };
+
+        let mut nn = SequentialModel::new(4);
+        nn.push_layer("Conv2D".to_string(), Layer::Conv2d(conv))
+            .unwrap();
+        nn.push_layer("MaxPool2D".to_string(), Layer::MaxPool2d(max_pool))
+            .unwrap();
+        nn.push_layer("Flatten".to_string(), Layer::Flatten(FlattenLayer::new()))
+            .unwrap();
+        nn.push_layer(
+            "Dense".to_string(),
+            Layer::Dense(DenseLayer::new(dense_input_size, 1, None)),
+        )
+        .unwrap();
+
+        let outputs = nn.forward_train(std::slice::from_ref(&input)).unwrap();
+        assert_eq!(outputs[0], nn.forward(&input).unwrap());
+
+        let grad_inputs = nn.backward(&[Array::ones((1, 1, 1))]).unwrap();
+        assert_eq!(grad_inputs[0].shape(), input.shape());
+
+        // max pooling is piecewise linear; a 1x1 kernel only scales the
+        // distinct inputs, so every pooling window stays far from ties and a
+        // small step keeps the argmax
+        let epsilon = 1e-3;
+        for (index, grad) in grad_inputs[0].indexed_iter() {
+            let mut shifted = input.clone();
+            shifted[index] += epsilon;
+            let numeric = (nn.forward(&shifted).unwrap().sum() - outputs[0].sum()) / epsilon;
+            assert_relative_eq!(*grad, numeric, epsilon = 5e-2);
+        }
+    }
+
+    #[test]
+    fn dropout_modes() {
+        let input = Array::ones((10, 10, 4));
+        let mut dropout = DropoutLayer::new(0.5).unwrap();
+        assert_eq!(dropout.forward(&input, Mode::Inference).unwrap(), input);
+        assert!(DropoutLayer::new(1.0).is_err());
+
+        let output = &dropout.forward_train(std::slice::from_ref(&input)).unwrap()[0];
+        let kept = output.iter().filter(|x| **x > 0.0).count();
+        assert!(output
+            .iter()
+            .all(|x| x.abs() < f32::EPSILON || (x - 2.0).abs() < f32::EPSILON));
+        assert!((100..300).contains(&kept));
+
+        let grad_input = &dropout.backward(&[Array::ones((10, 10, 4))]).unwrap()[0];
+        assert_eq!(grad_input, output);
+
+        let mut nn = SequentialModel::new(1);
+        nn.push_layer("Dropout".to_string(), Layer::Dropout(dropout))
+            .unwrap();
+        assert_eq!(nn.forward(&input).unwrap(), input);
+        nn.set_mode(Mode::Training);
+        assert_ne!(nn.forward(&input).unwrap(), input);
+    }
+
+    #[test]
+    fn batch_norm_layouts() {
+        // seeded, as the gradient check of random batches misses the
+        // tolerance in about one run out of sixty
+        let mut rng = StdRng::seed_from_u64(0);
+        let images: Vec<_> = (0..4)
+            .map(|_| Array::random_using((3, 3, 2), Uniform::new(0.0, 10.0), &mut rng))
+            .collect();
+        let mut batch_norm = BatchNormLayer::for_conv2d(2, Some(0.0), None);
+        let outputs = batch_norm.forward_train(&images).unwrap();
+        for channel in 0..2 {
+            let values: Vec<f32> = outputs
+                .iter()
+                .flat_map(|output| output.index_axis(Axis(2), channel).to_owned())
+                .collect();
+            let mean = values.iter().sum::<f32>() / 36.0;
+            let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 36.0;
+            assert_relative_eq!(mean, 0.0, epsilon = 1e-5);
+            assert_relative_eq!(variance, 1.0, epsilon = 1e-3);
+        }
+        // with momentum 0 the running statistics are the last batch ones
+        assert_relative_eq!(
+            batch_norm.forward(&images[0]).unwrap()[[1, 2, 1]],
+            outputs[0][[1, 2, 1]],
+            epsilon = 1e-5
+        );
+        assert!(batch_norm.forward(&Array::zeros((3, 3, 3))).is_err());
+
+        // every value counts once when the samples differ in size
+        let mixed = vec![
+            Array::random_using((1, 2, 2), Uniform::new(0.0, 10.0), &mut rng),
+            Array::random_using((3, 4, 2), Uniform::new(0.0, 10.0), &mut rng),
+        ];
+        let outputs = batch_norm.forward_train(&mixed).unwrap();
+        let values: Vec<f32> = outputs
+            .iter()
+            .flat_map(|output| output.index_axis(Axis(2), 0).to_owned())
+            .collect();
+        assert_eq!(values.len(), 14);
+        let mean = values.iter().sum::<f32>() / 14.0;
+        let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 14.0;
+        assert_relative_eq!(mean, 0.0, epsilon = 1e-5);
+        assert_relative_eq!(variance, 1.0, epsilon = 1e-3);
+
+        let vectors: Vec<_> = (0..4)
+            .map(|_| Array::random_using((1, 5, 1), Uniform::new(-2.0, 2.0), &mut rng))
+            .collect();
+        let mut cases = [
+            (Layer::BatchNorm(batch_norm), &images),
+            (
+                Layer::BatchNorm(BatchNormLayer::for_dense(5, None, None)),
+                &vectors,
+            ),
+            (
+                Layer::BatchNorm(BatchNormLayer::for_conv2d(2, None, None)),
+                &mixed,
+            ),
+        ];
+        for (layer, inputs) in &mut cases {
+            let report = check_gradients_using(layer, inputs, None, &mut rng).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+
+        let mut nn = SequentialModel::new(1);
+        let [(layer, _), ..] = cases;
+        nn.push_layer("BatchNorm".to_string(), layer).unwrap();
+        assert_eq!(nn.weights().len(), 4);
+    }
+
+    #[test]
+    fn layer_and_group_norm() {
+        let image = Array::random((3, 3, 4), Uniform::new(0.0, 10.0));
+        let layer_norm = LayerNormLayer::for_channels(4, None);
+        let output = layer_norm.forward(&image).unwrap();
+        // every position is normalized over its channels
+        let position = output.slice(s![1, 2, ..]);
+        assert_relative_eq!(position.mean().unwrap(), 0.0, epsilon = 1e-5);
+
+        let group_norm = GroupNormLayer::new(2, 4, None).unwrap();
+        let output = group_norm.forward(&image).unwrap();
+        let group = output.slice(s![.., .., 2..4]);
+        let mean = group.mean().unwrap();
+        assert_relative_eq!(mean, 0.0, epsilon = 1e-5);
+        assert_relative_eq!(
+            group.mapv(|x| (x - mean).powi(2)).mean().unwrap(),
+            1.0,
+            epsilon = 1e-3
+        );
+        assert!(GroupNormLayer::new(3, 4, None).is_err());
+
+        // seeded, as normalizing a handful of values that nearly coincide
+        // amplifies f32 rounding up to 1 / sqrt(epsilon) times, which random
+        // inputs hit in about one run out of a hundred
+        let mut rng = StdRng::seed_from_u64(0);
+        let images: Vec<_> = (0..2)
+            .map(|_| Array::random_using((3, 3, 4), Uniform::new(-2.0, 2.0), &mut rng))
+            .collect();
+        let vectors: Vec<_> = (0..2)
+            .map(|_| Array::random_using((1, 5, 1), Uniform::new(-2.0, 2.0), &mut rng))
+            .collect();
+        let mut cases = [
+            (Layer::LayerNorm(layer_norm), &images),
+            (
+                Layer::LayerNorm(LayerNormLayer::for_dense(5, None)),
+                &vectors,
+            ),
+            (Layer::GroupNorm(group_norm), &images),
+            (
+                Layer::GroupNorm(GroupNormLayer::instance_norm(4, None).unwrap()),
+                &images,
+            ),
+        ];
+        for (layer, inputs) in &mut cases {
+            let report = check_gradients_using(layer, inputs, None, &mut rng).unwrap();
+            assert!(report.passes(1e-2), "{report:?}");
+        }
+    }
+
     #[test]
     fn flatten_basic_test() {
         let input = array![[
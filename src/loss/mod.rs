@@ -0,0 +1,213 @@
+// loss functions
+//
+// Every loss returns the scalar loss of one sample along with its gradient
+// with respect to the predictions.
+
+use std::error::Error;
+
+use ndarray::{Array, Ix3, Zip};
+
+// Keeps logarithms finite when a probability reaches 0 or 1
+const PROBABILITY_EPSILON: f32 = 1e-7;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum LossFunctionType {
+    MeanSquaredError,
+    MeanAbsoluteError,
+    Huber(f32),
+    BinaryCrossEntropy,
+    CategoricalCrossEntropy,
+    SparseCategoricalCrossEntropy,
+    CategoricalCrossEntropyFromLogits,
+}
+
+impl LossFunctionType {
+    pub fn compute(
+        &self,
+        predictions: &Array<f32, Ix3>,
+        targets: &Array<f32, Ix3>,
+    ) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+        match self {
+            LossFunctionType::MeanSquaredError => mean_squared_error(predictions, targets),
+            LossFunctionType::MeanAbsoluteError => mean_absolute_error(predictions, targets),
+            LossFunctionType::Huber(delta) => huber(predictions, targets, *delta),
+            LossFunctionType::BinaryCrossEntropy => binary_cross_entropy(predictions, targets),
+            LossFunctionType::CategoricalCrossEntropy => {
+                categorical_cross_entropy(predictions, targets)
+            }
+            LossFunctionType::SparseCategoricalCrossEntropy => {
+                sparse_categorical_cross_entropy(predictions, targets)
+            }
+            LossFunctionType::CategoricalCrossEntropyFromLogits => {
+                categorical_cross_entropy_from_logits(predictions, targets)
+            }
+        }
+    }
+}
+
+#[expect(clippy::cast_precision_loss)]
+pub fn mean_squared_error(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(predictions, targets)?;
+    let size = predictions.len() as f32;
+    let error = predictions - targets;
+    let loss = error.map(|e| e.powi(2)).sum() / size;
+    Ok((loss, error * (2.0 / size)))
+}
+
+#[expect(clippy::cast_precision_loss)]
+pub fn mean_absolute_error(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(predictions, targets)?;
+    let size = predictions.len() as f32;
+    let error = predictions - targets;
+    let loss = error.map(|e| e.abs()).sum() / size;
+    Ok((loss, error.map(|e| sign(*e) / size)))
+}
+
+// Quadratic for errors smaller than delta and linear beyond it
+#[expect(clippy::cast_precision_loss)]
+pub fn huber(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+    delta: f32,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(predictions, targets)?;
+    let size = predictions.len() as f32;
+    let error = predictions - targets;
+    let loss = error
+        .map(|e| {
+            if e.abs() <= delta {
+                0.5 * e.powi(2)
+            } else {
+                delta * (e.abs() - 0.5 * delta)
+            }
+        })
+        .sum()
+        / size;
+    let gradient = error.map(|e| {
+        if e.abs() <= delta {
+            e / size
+        } else {
+            delta * sign(*e) / size
+        }
+    });
+    Ok((loss, gradient))
+}
+
+#[expect(clippy::cast_precision_loss)]
+pub fn binary_cross_entropy(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(predictions, targets)?;
+    let size = predictions.len() as f32;
+    let probabilities = predictions.map(|p| clip_probability(*p));
+    let loss = -Zip::from(&probabilities)
+        .and(targets)
+        .fold(0.0, |sum, p, t| {
+            sum + t * p.ln() + (1.0 - t) * (1.0 - p).ln()
+        })
+        / size;
+    let gradient = Zip::from(&probabilities)
+        .and(targets)
+        .map_collect(|p, t| (p - t) / (p * (1.0 - p)) / size);
+    Ok((loss, gradient))
+}
+
+// Targets are one-hot (or any probability distribution) over the predictions
+pub fn categorical_cross_entropy(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(predictions, targets)?;
+    let probabilities = predictions.map(|p| clip_probability(*p));
+    let loss = -Zip::from(&probabilities)
+        .and(targets)
+        .fold(0.0, |sum, p, t| sum + t * p.ln());
+    let gradient = Zip::from(&probabilities)
+        .and(targets)
+        .map_collect(|p, t| -t / p);
+    Ok((loss, gradient))
+}
+
+// The target holds the index of the right class, counted over the predictions
+// in their logical order
+#[expect(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn sparse_categorical_cross_entropy(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    let class = match targets.iter().next() {
+        Some(class) if targets.len() == 1 && *class >= 0.0 => *class as usize,
+        _ => return Err(Box::new(LossError::InvalidClassError)),
+    };
+    let Some(prediction) = predictions.iter().nth(class) else {
+        return Err(Box::new(LossError::InvalidClassError));
+    };
+
+    let probability = clip_probability(*prediction);
+    let mut gradient = Array::zeros(predictions.raw_dim());
+    if let Some(class_gradient) = gradient.iter_mut().nth(class) {
+        *class_gradient = -1.0 / probability;
+    }
+    Ok((-probability.ln(), gradient))
+}
+
+// Categorical cross-entropy of softmax(logits), fused so that no probability
+// is ever computed close to zero. Use it with a last layer whose activation is
+// ActivationFunctionType::None.
+pub fn categorical_cross_entropy_from_logits(
+    logits: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(f32, Array<f32, Ix3>), Box<dyn Error>> {
+    check_shapes(logits, targets)?;
+    let max_logit = logits.fold(f32::NEG_INFINITY, |max, x| max.max(*x));
+    let shifted = logits.map(|x| x - max_logit);
+    let log_sum_exp = shifted.map(|x| x.exp()).sum().ln();
+    let log_probabilities = shifted.map(|x| x - log_sum_exp);
+
+    let loss = -(targets * &log_probabilities).sum();
+    let targets_sum = targets.sum();
+    let gradient = Zip::from(&log_probabilities)
+        .and(targets)
+        .map_collect(|log_p, t| log_p.exp() * targets_sum - t);
+    Ok((loss, gradient))
+}
+
+fn check_shapes(
+    predictions: &Array<f32, Ix3>,
+    targets: &Array<f32, Ix3>,
+) -> Result<(), Box<dyn Error>> {
+    if predictions.shape() == targets.shape() {
+        Ok(())
+    } else {
+        Err(Box::new(LossError::ShapeMismatchError))
+    }
+}
+
+fn clip_probability(p: f32) -> f32 {
+    p.clamp(PROBABILITY_EPSILON, 1.0 - PROBABILITY_EPSILON)
+}
+
+fn sign(x: f32) -> f32 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LossError {
+    #[error("predictions and targets should have the same shape")]
+    ShapeMismatchError,
+    #[error("sparse targets should hold a single valid class index")]
+    InvalidClassError,
+}
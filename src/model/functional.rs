@@ -0,0 +1,447 @@
+use std::error::Error;
+
+use ndarray::{concatenate, Array, ArrayView, Axis, Ix3};
+
+use crate::layer::{Layer, Mode, Parameter};
+
+// Nodes combining the outputs of several nodes of a FunctionalModel. Add,
+// Multiply and Average are element-wise and need inputs of the same shape,
+// Concatenate stacks its inputs along an axis (1 for dense outputs, 2 for the
+// channels of images).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Merge {
+    Add,
+    Concatenate { axis: usize },
+    Multiply,
+    Average,
+}
+
+impl Merge {
+    #[expect(clippy::cast_precision_loss)]
+    pub fn forward(self, inputs: &[&Array<f32, Ix3>]) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        if let Merge::Concatenate { axis } = self {
+            let views: Vec<ArrayView<f32, Ix3>> = inputs.iter().map(|input| input.view()).collect();
+            return Ok(concatenate(Axis(axis), &views)?);
+        }
+        if inputs
+            .iter()
+            .any(|input| input.shape() != inputs[0].shape())
+        {
+            return Err(Box::new(FunctionalModelError::MergeShapeError));
+        }
+
+        let mut output = inputs[0].clone();
+        for input in &inputs[1..] {
+            match self {
+                Merge::Multiply => output *= *input,
+                _ => output += *input,
+            }
+        }
+        if self == Merge::Average {
+            output /= inputs.len() as f32;
+        }
+        Ok(output)
+    }
+
+    // Gradient of every input of the merge from the gradient of its output
+    #[expect(clippy::cast_precision_loss)]
+    pub fn backward(
+        self,
+        inputs: &[&Array<f32, Ix3>],
+        grad_output: &Array<f32, Ix3>,
+    ) -> Vec<Array<f32, Ix3>> {
+        match self {
+            Merge::Add => vec![grad_output.clone(); inputs.len()],
+            Merge::Average => vec![grad_output / inputs.len() as f32; inputs.len()],
+            Merge::Multiply => (0..inputs.len())
+                .map(|index| {
+                    let mut grad_input = grad_output.clone();
+                    for (other, input) in inputs.iter().enumerate() {
+                        if other != index {
+                            grad_input *= *input;
+                        }
+                    }
+                    grad_input
+                })
+                .collect(),
+            Merge::Concatenate { axis } => {
+                let mut start = 0;
+                inputs
+                    .iter()
+                    .map(|input| {
+                        let end = start + input.len_of(Axis(axis));
+                        let grad_input = grad_output
+                            .slice_axis(Axis(axis), (start..end).into())
+                            .to_owned();
+                        start = end;
+                        grad_input
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+enum Node {
+    Input,
+    Layer { layer: Box<Layer>, input: usize },
+    Merge { merge: Merge, inputs: Vec<usize> },
+}
+
+// Model whose layers form a directed acyclic graph. Nodes are added once the
+// nodes they read from exist, so they are always in topological order, and
+// are referred to by their unique names.
+pub struct FunctionalModel {
+    nodes: Vec<Node>,
+    node_names: Vec<String>,
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+    mode: Mode,
+    // Output of every node for each sample of the last forward_train batch
+    cache: Vec<Vec<Array<f32, Ix3>>>,
+}
+
+impl FunctionalModel {
+    pub fn new(nodes_size: usize) -> Self {
+        FunctionalModel {
+            nodes: Vec::with_capacity(nodes_size),
+            node_names: Vec::with_capacity(nodes_size),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            mode: Mode::Inference,
+            cache: Vec::new(),
+        }
+    }
+
+    // Inputs are given to predict, forward and forward_train in the order
+    // they were added
+    pub fn add_input(&mut self, input_name: String) -> Result<(), Box<dyn Error>> {
+        self.push_node(input_name, Node::Input)?;
+        self.inputs.push(self.nodes.len() - 1);
+        Ok(())
+    }
+
+    pub fn add_layer(
+        &mut self,
+        layer_name: String,
+        layer: Layer,
+        input_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let input = self.node_index(input_name)?;
+        self.push_node(
+            layer_name,
+            Node::Layer {
+                layer: Box::new(layer),
+                input,
+            },
+        )
+    }
+
+    pub fn add_merge(
+        &mut self,
+        merge_name: String,
+        merge: Merge,
+        input_names: &[&str],
+    ) -> Result<(), Box<dyn Error>> {
+        if input_names.len() < 2 {
+            return Err(Box::new(FunctionalModelError::MergeInputsError));
+        }
+        if let Merge::Concatenate { axis } = merge {
+            if axis > 2 {
+                return Err(Box::new(FunctionalModelError::MergeAxisError));
+            }
+        }
+        let inputs = input_names
+            .iter()
+            .map(|input_name| self.node_index(input_name))
+            .collect::<Result<_, _>>()?;
+        self.push_node(merge_name, Node::Merge { merge, inputs })
+    }
+
+    // Nodes whose outputs are returned, in this order, by the model
+    pub fn set_outputs(&mut self, output_names: &[&str]) -> Result<(), Box<dyn Error>> {
+        self.outputs = output_names
+            .iter()
+            .map(|output_name| self.node_index(output_name))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    pub fn input_names(&self) -> Vec<&str> {
+        self.inputs
+            .iter()
+            .map(|&node| self.node_names[node].as_str())
+            .collect()
+    }
+
+    pub fn output_names(&self) -> Vec<&str> {
+        self.outputs
+            .iter()
+            .map(|&node| self.node_names[node].as_str())
+            .collect()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Mode used by forward. predict always runs in inference and
+    // forward_train in training, whatever the mode is.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    fn push_node(&mut self, node_name: String, node: Node) -> Result<(), Box<dyn Error>> {
+        if self.node_names.contains(&node_name) {
+            return Err(Box::new(FunctionalModelError::DuplicateNameError(
+                node_name,
+            )));
+        }
+        self.nodes.push(node);
+        self.node_names.push(node_name);
+        Ok(())
+    }
+
+    fn node_index(&self, node_name: &str) -> Result<usize, Box<dyn Error>> {
+        self.node_names
+            .iter()
+            .position(|name| name == node_name)
+            .ok_or_else(|| FunctionalModelError::UnknownNodeError(node_name.to_string()).into())
+    }
+
+    fn check_graph(&self, input_count: usize) -> Result<(), Box<dyn Error>> {
+        if self.outputs.is_empty() {
+            return Err(Box::new(FunctionalModelError::MissingOutputsError));
+        }
+        if input_count != self.inputs.len() {
+            return Err(Box::new(FunctionalModelError::InputCountError));
+        }
+        Ok(())
+    }
+}
+
+impl FunctionalModel {
+    // One input per input node, returns one output per output node
+    pub fn predict(
+        &self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.forward_with_mode(inputs, Mode::Inference)
+    }
+
+    pub fn forward(
+        &self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.forward_with_mode(inputs, self.mode)
+    }
+
+    fn forward_with_mode(
+        &self,
+        inputs: &[Array<f32, Ix3>],
+        mode: Mode,
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        self.check_graph(inputs.len())?;
+
+        let mut values: Vec<Array<f32, Ix3>> = Vec::with_capacity(self.nodes.len());
+        let mut model_inputs = inputs.iter();
+        for node in &self.nodes {
+            let value = match node {
+                Node::Input => model_inputs
+                    .next()
+                    .ok_or(FunctionalModelError::InputCountError)?
+                    .clone(),
+                Node::Layer { layer, input } => layer.forward(&values[*input], mode)?,
+                Node::Merge { merge, inputs } => {
+                    let merge_inputs: Vec<_> = inputs.iter().map(|&input| &values[input]).collect();
+                    merge.forward(&merge_inputs)?
+                }
+            };
+            values.push(value);
+        }
+
+        Ok(self
+            .outputs
+            .iter()
+            .map(|&output| values[output].clone())
+            .collect())
+    }
+}
+
+impl FunctionalModel {
+    // Forwards a batch while every layer keeps what it needs for backward.
+    // inputs[i] is the batch of the i-th input node, and the result holds the
+    // batch of every output node.
+    #[expect(clippy::type_complexity)]
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Vec<Array<f32, Ix3>>],
+    ) -> Result<Vec<Vec<Array<f32, Ix3>>>, Box<dyn Error>> {
+        self.check_graph(inputs.len())?;
+        if inputs.iter().any(|batch| batch.len() != inputs[0].len()) {
+            return Err(Box::new(FunctionalModelError::BatchSizeError));
+        }
+
+        self.cache.clear();
+        let mut model_inputs = inputs.iter();
+        for node in &mut self.nodes {
+            let values = match node {
+                Node::Input => model_inputs
+                    .next()
+                    .ok_or(FunctionalModelError::InputCountError)?
+                    .clone(),
+                Node::Layer { layer, input } => layer.forward_train(&self.cache[*input])?,
+                Node::Merge { merge, inputs } => (0..self.cache[inputs[0]].len())
+                    .map(|sample| {
+                        let merge_inputs: Vec<_> = inputs
+                            .iter()
+                            .map(|&input| &self.cache[input][sample])
+                            .collect();
+                        merge.forward(&merge_inputs)
+                    })
+                    .collect::<Result<_, _>>()?,
+            };
+            self.cache.push(values);
+        }
+
+        Ok(self
+            .outputs
+            .iter()
+            .map(|&output| self.cache[output].clone())
+            .collect())
+    }
+
+    // Backpropagates the gradients of every output node for the last
+    // forward_train batch and returns the gradients of every input node.
+    // Nodes read by several others get the sum of their gradients, layer
+    // gradients are accumulated until zero_gradients is called.
+    #[expect(clippy::type_complexity)]
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Vec<Array<f32, Ix3>>],
+    ) -> Result<Vec<Vec<Array<f32, Ix3>>>, Box<dyn Error>> {
+        if self.cache.len() != self.nodes.len() {
+            return Err(Box::new(FunctionalModelError::MissingForwardError));
+        }
+        if grad_outputs.len() != self.outputs.len() {
+            return Err(Box::new(FunctionalModelError::OutputCountError));
+        }
+
+        let mut grads: Vec<Option<Vec<Array<f32, Ix3>>>> = vec![None; self.nodes.len()];
+        for (&output, grad_output) in self.outputs.iter().zip(grad_outputs) {
+            accumulate_gradients(&mut grads, output, grad_output.clone());
+        }
+
+        for (node_index, node) in self.nodes.iter_mut().enumerate().rev() {
+            // nodes that do not lead to an output get no gradient
+            let Some(grad) = grads[node_index].take() else {
+                continue;
+            };
+            match node {
+                Node::Input => grads[node_index] = Some(grad),
+                Node::Layer { layer, input } => {
+                    let grad_input = layer.backward(&grad)?;
+                    accumulate_gradients(&mut grads, *input, grad_input);
+                }
+                Node::Merge { merge, inputs } => {
+                    let mut grad_inputs = vec![Vec::with_capacity(grad.len()); inputs.len()];
+                    for (sample, grad_output) in grad.iter().enumerate() {
+                        let merge_inputs: Vec<_> = inputs
+                            .iter()
+                            .map(|&input| &self.cache[input][sample])
+                            .collect();
+                        for (grad_input, sample_grad) in grad_inputs
+                            .iter_mut()
+                            .zip(merge.backward(&merge_inputs, grad_output))
+                        {
+                            grad_input.push(sample_grad);
+                        }
+                    }
+                    for (&input, grad_input) in inputs.iter().zip(grad_inputs) {
+                        accumulate_gradients(&mut grads, input, grad_input);
+                    }
+                }
+            }
+        }
+
+        Ok(self
+            .inputs
+            .iter()
+            .map(|&input| {
+                grads[input].take().unwrap_or_else(|| {
+                    self.cache[input]
+                        .iter()
+                        .map(|value| Array::zeros(value.raw_dim()))
+                        .collect()
+                })
+            })
+            .collect())
+    }
+
+    pub fn zero_gradients(&mut self) {
+        for layer in self.layers_mut() {
+            layer.zero_gradients();
+        }
+    }
+
+    // Every trainable tensor of the model, in the order the layers were added
+    pub fn parameters(&self) -> Vec<&Array<f32, Ix3>> {
+        self.nodes
+            .iter()
+            .filter_map(|node| match node {
+                Node::Layer { layer, .. } => Some(layer.as_ref()),
+                _ => None,
+            })
+            .flat_map(Layer::parameters)
+            .collect()
+    }
+
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.layers_mut().flat_map(Layer::parameters_mut).collect()
+    }
+
+    fn layers_mut(&mut self) -> impl Iterator<Item = &mut Layer> {
+        self.nodes.iter_mut().filter_map(|node| match node {
+            Node::Layer { layer, .. } => Some(layer.as_mut()),
+            _ => None,
+        })
+    }
+}
+
+fn accumulate_gradients(
+    grads: &mut [Option<Vec<Array<f32, Ix3>>>],
+    node: usize,
+    grad: Vec<Array<f32, Ix3>>,
+) {
+    if let Some(sum) = &mut grads[node] {
+        for (sum, grad) in sum.iter_mut().zip(&grad) {
+            *sum += grad;
+        }
+    } else {
+        grads[node] = Some(grad);
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FunctionalModelError {
+    #[error("node name {0} is already used")]
+    DuplicateNameError(String),
+    #[error("no node is named {0}")]
+    UnknownNodeError(String),
+    #[error("merge nodes need at least two inputs")]
+    MergeInputsError,
+    #[error("concatenate axis should be 0, 1 or 2")]
+    MergeAxisError,
+    #[error("merged inputs should have the same shape")]
+    MergeShapeError,
+    #[error("the model outputs should be set before running it")]
+    MissingOutputsError,
+    #[error("the model should get one input per input node")]
+    InputCountError,
+    #[error("the model should get one gradient per output node")]
+    OutputCountError,
+    #[error("every input should have the same batch size")]
+    BatchSizeError,
+    #[error("backward needs a forward_train call")]
+    MissingForwardError,
+}
@@ -0,0 +1,13 @@
+// Mean loss of every epoch run by fit
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    pub loss: Vec<f32>,
+    // Empty when fit was called without a validation split
+    pub validation_loss: Vec<f32>,
+}
+
+impl History {
+    pub fn epochs(&self) -> usize {
+        self.loss.len()
+    }
+}
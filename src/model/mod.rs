@@ -1,24 +1,46 @@
+pub mod functional;
+pub mod history;
 pub mod sequential;
+pub mod summary;
+pub mod weights;
 
 use std::error::Error;
 
+use functional::{FunctionalModel, FunctionalModelError};
 use ndarray::{Array, Ix3};
 use sequential::SequentialModel;
 
 pub enum Model {
     Sequential(SequentialModel),
+    Functional(FunctionalModel),
 }
 
+// predict runs the model in inference, forward in the mode set on the model,
+// with the same semantics as SequentialModel and FunctionalModel
 impl Model {
     fn predict(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
         match &self {
             Model::Sequential(sequential) => sequential.predict(input),
+            Model::Functional(functional) => {
+                single_output(functional.predict(std::slice::from_ref(input))?)
+            }
         }
     }
 
     fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
         match &self {
-            Model::Sequential(sequential) => sequential.predict(input),
+            Model::Sequential(sequential) => sequential.forward(input),
+            Model::Functional(functional) => {
+                single_output(functional.forward(std::slice::from_ref(input))?)
+            }
         }
     }
 }
+
+// Models with several outputs should be run through FunctionalModel directly
+fn single_output(mut outputs: Vec<Array<f32, Ix3>>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+    if outputs.len() != 1 {
+        return Err(Box::new(FunctionalModelError::OutputCountError));
+    }
+    Ok(outputs.remove(0))
+}
@@ -1,12 +1,27 @@
-use std::error::Error;
+use std::{error::Error, path::Path};
 
 use ndarray::{Array, Ix3};
+use rand::seq::SliceRandom;
 
-use crate::layer::Layer;
+use crate::{
+    callback::{BatchLogs, Callback, EpochLogs},
+    layer::{Layer, Mode, Parameter},
+    loss::LossFunctionType,
+    model::{
+        history::History,
+        summary::{format_summary, LayerSummary},
+        weights::{read_weights, write_weights, WeightsError},
+    },
+    optim::Optimizer,
+};
 
 pub struct SequentialModel {
     layers: Vec<Layer>,
     layer_names: Vec<String>,
+    mode: Mode,
+    input_dim: Option<(usize, usize, usize)>,
+    // Output shape of every layer, only known along with the input shape
+    output_dims: Vec<(usize, usize, usize)>,
 }
 
 impl SequentialModel {
@@ -14,37 +29,420 @@ impl SequentialModel {
         SequentialModel {
             layers: Vec::with_capacity(layers_size),
             layer_names: Vec::with_capacity(layers_size),
+            mode: Mode::Inference,
+            input_dim: None,
+            output_dims: Vec::with_capacity(layers_size),
         }
     }
 
-    pub fn push_layer(&mut self, layer_name: String, layer: Layer) {
+    // Model taking inputs of shape input_dim. push_layer then checks that
+    // every layer can take the output of the previous one, and inputs of
+    // another shape are rejected.
+    pub fn with_input_dim(input_dim: (usize, usize, usize), layers_size: usize) -> Self {
+        SequentialModel {
+            input_dim: Some(input_dim),
+            ..Self::new(layers_size)
+        }
+    }
+
+    pub fn push_layer(&mut self, layer_name: String, layer: Layer) -> Result<(), Box<dyn Error>> {
+        if let Some(input_dim) = self.output_dim() {
+            let Some(output_dim) = layer.output_dim(input_dim) else {
+                return Err(Box::new(SequentialModelError::LayerShapeError {
+                    layer_name,
+                    input_dim,
+                }));
+            };
+            self.output_dims.push(output_dim);
+        }
         self.layers.push(layer);
         self.layer_names.push(layer_name);
+        Ok(())
+    }
+
+    pub fn input_dim(&self) -> Option<(usize, usize, usize)> {
+        self.input_dim
+    }
+
+    // Shape of the model output, the input shape while there is no layer
+    pub fn output_dim(&self) -> Option<(usize, usize, usize)> {
+        self.output_dims.last().copied().or(self.input_dim)
+    }
+
+    // Output shape of every layer, empty without an input shape
+    pub fn output_dims(&self) -> &[(usize, usize, usize)] {
+        &self.output_dims
+    }
+
+    // Table of every layer with its type, output shape, parameter count and
+    // the memory of its output for one sample, followed by the totals. Shapes
+    // and activation memory are only known when the model has its input
+    // shape.
+    pub fn summary(&self) -> String {
+        let layers: Vec<LayerSummary> = self
+            .layers
+            .iter()
+            .zip(&self.layer_names)
+            .enumerate()
+            .map(|(index, (layer, layer_name))| LayerSummary {
+                name: layer_name.clone(),
+                layer_type: layer.type_name(),
+                output_dim: self.output_dims.get(index).copied(),
+                parameters: layer
+                    .parameters()
+                    .iter()
+                    .map(|parameter| parameter.len())
+                    .sum(),
+            })
+            .collect();
+        format_summary(&layers)
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    // Mode used by forward. predict and evaluate always run in inference and
+    // forward_train in training, whatever the mode is.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
     }
 }
 
 impl SequentialModel {
+    // predict and forward both chain the layers, each one reading the output
+    // of the previous one, and never add skip connections on their own (wrap
+    // layers in Layer::Residual for that). predict always runs in inference,
+    // forward runs in the mode given to set_mode.
     pub fn predict(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
-        let mut current = input.clone();
+        self.forward_with_mode(input, Mode::Inference)
+    }
+
+    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.forward_with_mode(input, self.mode)
+    }
 
+    fn forward_with_mode(
+        &self,
+        input: &Array<f32, Ix3>,
+        mode: Mode,
+    ) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
+        self.check_input_dim(input)?;
+        let mut result = input.clone();
         for layer in &self.layers {
-            let result = match layer.forward(&current) {
-                Ok(forward_result) => forward_result,
-                Err(err) => {
-                    return Err(err);
+            result = layer.forward(&result, mode)?;
+        }
+        Ok(result)
+    }
+
+    fn check_input_dim(&self, input: &Array<f32, Ix3>) -> Result<(), Box<dyn Error>> {
+        match self.input_dim {
+            Some(input_dim) if input.dim() != input_dim => {
+                Err(Box::new(SequentialModelError::InputShapeError {
+                    expected: input_dim,
+                    actual: input.dim(),
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl SequentialModel {
+    // Forwards a batch while every layer keeps what it needs for backward
+    pub fn forward_train(
+        &mut self,
+        inputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        for input in inputs {
+            self.check_input_dim(input)?;
+        }
+        let mut results = inputs.to_vec();
+        for layer in &mut self.layers {
+            results = layer.forward_train(&results)?;
+        }
+        Ok(results)
+    }
+
+    // Backpropagates the gradients of the last forward_train batch, layer
+    // gradients are accumulated until zero_gradients is called
+    pub fn backward(
+        &mut self,
+        grad_outputs: &[Array<f32, Ix3>],
+    ) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+        let mut grads = grad_outputs.to_vec();
+        for layer in self.layers.iter_mut().rev() {
+            grads = layer.backward(&grads)?;
+        }
+        Ok(grads)
+    }
+
+    pub fn zero_gradients(&mut self) {
+        for layer in &mut self.layers {
+            layer.zero_gradients();
+        }
+    }
+
+    // Every trainable tensor of the model, always in the same order
+    pub fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        self.layers
+            .iter_mut()
+            .flat_map(Layer::parameters_mut)
+            .collect()
+    }
+}
+
+impl SequentialModel {
+    // Copy of every trainable tensor, in the order of parameters_mut, followed
+    // by the buffers of every layer (e.g. batch normalization statistics)
+    pub fn weights(&self) -> Vec<Array<f32, Ix3>> {
+        self.weight_tensors().into_iter().cloned().collect()
+    }
+
+    pub fn set_weights(&mut self, weights: &[Array<f32, Ix3>]) -> Result<(), Box<dyn Error>> {
+        let mut tensors: Vec<&mut Array<f32, Ix3>> = Vec::new();
+        let mut buffers = Vec::new();
+        for layer in &mut self.layers {
+            let (layer_parameters, layer_buffers) = layer.parameters_and_buffers_mut();
+            tensors.extend(
+                layer_parameters
+                    .into_iter()
+                    .map(|parameter| parameter.value),
+            );
+            buffers.extend(layer_buffers);
+        }
+        tensors.extend(buffers);
+
+        let shapes_match = tensors.len() == weights.len()
+            && tensors
+                .iter()
+                .zip(weights)
+                .all(|(tensor, weight)| tensor.shape() == weight.shape());
+        if !shapes_match {
+            return Err(Box::new(WeightsError::ShapeMismatchError));
+        }
+        for (tensor, weight) in tensors.into_iter().zip(weights) {
+            tensor.assign(weight);
+        }
+        Ok(())
+    }
+
+    pub fn save_weights(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        write_weights(path, &self.weight_tensors())
+    }
+
+    // The model should have the same architecture as the one that saved them
+    pub fn load_weights(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.set_weights(&read_weights(path)?)
+    }
+
+    fn weight_tensors(&self) -> Vec<&Array<f32, Ix3>> {
+        let parameters = self.layers.iter().flat_map(Layer::parameters);
+        let buffers = self.layers.iter().flat_map(Layer::buffers);
+        parameters.chain(buffers).collect()
+    }
+}
+
+impl SequentialModel {
+    // Trains the model with mini-batch gradient descent. The last
+    // validation_split fraction of the samples is kept out of training and only
+    // used to compute the validation loss of every epoch.
+    #[expect(clippy::too_many_arguments)]
+    pub fn fit(
+        &mut self,
+        x: &[Array<f32, Ix3>],
+        y: &[Array<f32, Ix3>],
+        loss: LossFunctionType,
+        optimizer: &mut Optimizer,
+        epochs: usize,
+        batch_size: usize,
+        validation_split: f32,
+    ) -> Result<History, Box<dyn Error>> {
+        self.fit_with_callbacks(
+            x,
+            y,
+            loss,
+            optimizer,
+            epochs,
+            batch_size,
+            validation_split,
+            &mut [],
+        )
+    }
+
+    // Same as fit, calling every callback around each epoch and batch. Training
+    // ends early when a callback breaks at the end of an epoch.
+    #[expect(clippy::too_many_arguments)]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn fit_with_callbacks(
+        &mut self,
+        x: &[Array<f32, Ix3>],
+        y: &[Array<f32, Ix3>],
+        loss: LossFunctionType,
+        optimizer: &mut Optimizer,
+        epochs: usize,
+        batch_size: usize,
+        validation_split: f32,
+        callbacks: &mut [&mut dyn Callback],
+    ) -> Result<History, Box<dyn Error>> {
+        if x.len() != y.len() {
+            return Err(Box::new(SequentialModelError::SampleCountError));
+        }
+        if batch_size == 0 {
+            return Err(Box::new(SequentialModelError::BatchSizeError));
+        }
+        if !(0.0..1.0).contains(&validation_split) {
+            return Err(Box::new(SequentialModelError::ValidationSplitError));
+        }
+
+        let validation_size = (x.len() as f32 * validation_split).round() as usize;
+        let train_size = x.len() - validation_size;
+        if train_size == 0 {
+            return Err(Box::new(SequentialModelError::ValidationSplitError));
+        }
+        let (train_x, validation_x) = x.split_at(train_size);
+        let (train_y, validation_y) = y.split_at(train_size);
+
+        let mut history = History::default();
+        let mut indices: Vec<usize> = (0..train_size).collect();
+        let batches = train_size.div_ceil(batch_size);
+        let mut rng = rand::thread_rng();
+
+        for callback in callbacks.iter_mut() {
+            callback.on_train_begin(self)?;
+        }
+
+        for epoch in 0..epochs {
+            for callback in callbacks.iter_mut() {
+                callback.on_epoch_begin(self, epoch)?;
+            }
+            indices.shuffle(&mut rng);
+
+            let mut epoch_loss = 0.0;
+            for (batch, batch_indices) in indices.chunks(batch_size).enumerate() {
+                for callback in callbacks.iter_mut() {
+                    callback.on_batch_begin(self, batch)?;
+                }
+
+                let batch_x: Vec<_> = batch_indices.iter().map(|&i| train_x[i].clone()).collect();
+                let batch_y: Vec<_> = batch_indices.iter().map(|&i| &train_y[i]).collect();
+                let batch_loss = self.train_batch(&batch_x, &batch_y, loss, optimizer)?;
+                epoch_loss += batch_loss;
+
+                let batch_metrics = BatchLogs {
+                    epoch,
+                    epochs,
+                    batch,
+                    batches,
+                    loss: batch_loss / batch_indices.len() as f32,
+                };
+                for callback in callbacks.iter_mut() {
+                    callback.on_batch_end(self, &batch_metrics)?;
                 }
+            }
+            let train_loss = epoch_loss / train_size as f32;
+            history.loss.push(train_loss);
+
+            let mut validation_loss = None;
+            if validation_size > 0 {
+                let epoch_validation_loss = self.evaluate(validation_x, validation_y, loss)?;
+                history.validation_loss.push(epoch_validation_loss);
+                validation_loss = Some(epoch_validation_loss);
+            }
+            optimizer.end_epoch(Some(validation_loss.unwrap_or(train_loss)));
+
+            let epoch_metrics = EpochLogs {
+                epoch,
+                epochs,
+                loss: train_loss,
+                validation_loss,
+                learning_rate: optimizer.learning_rate,
             };
-            current = current + result;
+            let mut stop = false;
+            for callback in callbacks.iter_mut() {
+                stop |= callback.on_epoch_end(self, &epoch_metrics)?.is_break();
+            }
+            if stop {
+                break;
+            }
         }
 
-        Ok(current)
+        for callback in callbacks.iter_mut() {
+            callback.on_train_end(self)?;
+        }
+
+        Ok(history)
     }
 
-    pub fn forward(&self, input: &Array<f32, Ix3>) -> Result<Array<f32, Ix3>, Box<dyn Error>> {
-        let mut result = input.clone();
-        for layer in &self.layers {
-            result = layer.forward(&result)?;
+    // Mean loss of the model over the given samples
+    #[expect(clippy::cast_precision_loss)]
+    pub fn evaluate(
+        &self,
+        x: &[Array<f32, Ix3>],
+        y: &[Array<f32, Ix3>],
+        loss: LossFunctionType,
+    ) -> Result<f32, Box<dyn Error>> {
+        if x.len() != y.len() {
+            return Err(Box::new(SequentialModelError::SampleCountError));
         }
-        Ok(result)
+        let mut total_loss = 0.0;
+        for (input, target) in x.iter().zip(y) {
+            let (sample_loss, _) =
+                loss.compute(&self.forward_with_mode(input, Mode::Inference)?, target)?;
+            total_loss += sample_loss;
+        }
+        Ok(total_loss / x.len() as f32)
     }
+
+    // Runs forward, backward and one optimizer step over a batch, returning
+    // the summed loss of its samples
+    #[expect(clippy::cast_precision_loss)]
+    fn train_batch(
+        &mut self,
+        batch_x: &[Array<f32, Ix3>],
+        batch_y: &[&Array<f32, Ix3>],
+        loss: LossFunctionType,
+        optimizer: &mut Optimizer,
+    ) -> Result<f32, Box<dyn Error>> {
+        let batch_size = batch_x.len() as f32;
+        self.zero_gradients();
+
+        let outputs = self.forward_train(batch_x)?;
+        let mut batch_loss = 0.0;
+        let mut grad_outputs = Vec::with_capacity(outputs.len());
+        for (output, target) in outputs.iter().zip(batch_y) {
+            let (sample_loss, grad_output) = loss.compute(output, target)?;
+            batch_loss += sample_loss;
+            // gradients are averaged over the batch
+            grad_outputs.push(grad_output / batch_size);
+        }
+
+        self.backward(&grad_outputs)?;
+        optimizer.step(self)?;
+        Ok(batch_loss)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SequentialModelError {
+    #[error("x and y should have the same number of samples")]
+    SampleCountError,
+    #[error("batch size should be at least 1")]
+    BatchSizeError,
+    #[error("validation split should be in [0, 1) and leave training samples")]
+    ValidationSplitError,
+    #[error("layer {layer_name} cannot take an input of shape {input_dim:?}")]
+    LayerShapeError {
+        layer_name: String,
+        input_dim: (usize, usize, usize),
+    },
+    #[error("the model takes inputs of shape {expected:?}, got {actual:?}")]
+    InputShapeError {
+        expected: (usize, usize, usize),
+        actual: (usize, usize, usize),
+    },
 }
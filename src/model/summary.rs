@@ -0,0 +1,94 @@
+// Table returned by SequentialModel::summary. Memory is reported in bytes,
+// activation memory being the size of the layer output for one sample.
+
+pub struct LayerSummary {
+    pub name: String,
+    pub layer_type: &'static str,
+    // None when the model was built without its input shape
+    pub output_dim: Option<(usize, usize, usize)>,
+    pub parameters: usize,
+}
+
+impl LayerSummary {
+    pub fn activation_memory(&self) -> Option<usize> {
+        self.output_dim
+            .map(|(height, width, channels)| height * width * channels * size_of::<f32>())
+    }
+}
+
+pub fn format_summary(layers: &[LayerSummary]) -> String {
+    let headers = [
+        "Layer",
+        "Type",
+        "Output shape",
+        "Parameters",
+        "Activation memory",
+    ]
+    .map(String::from);
+    let rows: Vec<[String; 5]> = layers
+        .iter()
+        .map(|layer| {
+            [
+                layer.name.clone(),
+                layer.layer_type.to_string(),
+                layer
+                    .output_dim
+                    .map_or_else(|| "?".to_string(), |output_dim| format!("{output_dim:?}")),
+                layer.parameters.to_string(),
+                layer
+                    .activation_memory()
+                    .map_or_else(|| "?".to_string(), format_bytes),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.clone().map(|header| header.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let separator = "=".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1));
+
+    let total_parameters: usize = layers.iter().map(|layer| layer.parameters).sum();
+    let activation_memory: Option<usize> = layers.iter().map(LayerSummary::activation_memory).sum();
+
+    let mut lines = vec![format_row(&headers, &widths), separator.clone()];
+    lines.extend(rows.iter().map(|row| format_row(row, &widths)));
+    lines.push(separator);
+    lines.push(format!("Total parameters: {total_parameters}"));
+    lines.push(format!(
+        "Parameter memory: {}",
+        format_bytes(total_parameters * size_of::<f32>())
+    ));
+    lines.push(format!(
+        "Activation memory per sample: {}",
+        activation_memory.map_or_else(|| "?".to_string(), format_bytes)
+    ));
+    lines.join("\n") + "\n"
+}
+
+// Names are left aligned, numbers right aligned
+fn format_row(row: &[String; 5], widths: &[usize; 5]) -> String {
+    let [name, layer_type, output_dim, parameters, memory] = row;
+    let [name_width, type_width, output_width, parameters_width, memory_width] = *widths;
+    format!(
+        "{name:<name_width$}  {layer_type:<type_width$}  {output_dim:<output_width$}  \
+         {parameters:>parameters_width$}  {memory:>memory_width$}"
+    )
+}
+
+#[expect(clippy::cast_precision_loss)]
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
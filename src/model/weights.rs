@@ -0,0 +1,93 @@
+// Binary weights file: the MAGIC bytes, the number of tensors, then every
+// tensor as its three dimensions followed by its values in logical order. All
+// numbers are little endian, dimensions are u64 and values f32.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use ndarray::{Array, Ix3};
+
+const MAGIC: &[u8; 4] = b"CRNV";
+const SHAPE_BYTES: usize = 3 * 8;
+const VALUE_BYTES: usize = 4;
+
+pub fn write_weights(path: &Path, weights: &[&Array<f32, Ix3>]) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(weights.len() as u64).to_le_bytes())?;
+    for tensor in weights {
+        for len in tensor.shape() {
+            writer.write_all(&(*len as u64).to_le_bytes())?;
+        }
+        for value in *tensor {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_weights(path: &Path) -> Result<Vec<Array<f32, Ix3>>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    // bytes left after the header, so that corrupted lengths are rejected
+    // before anything is allocated for them
+    let mut remaining = usize::try_from(file.metadata()?.len())?
+        .checked_sub(MAGIC.len() + 8)
+        .ok_or(WeightsError::InvalidFileError)?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Box::new(WeightsError::InvalidFileError));
+    }
+
+    let tensor_count = read_len(&mut reader)?;
+    // every tensor takes at least the bytes of its shape
+    if tensor_count > remaining / SHAPE_BYTES {
+        return Err(Box::new(WeightsError::InvalidFileError));
+    }
+    let mut weights = Vec::with_capacity(tensor_count);
+    for _ in 0..tensor_count {
+        let shape = (
+            read_len(&mut reader)?,
+            read_len(&mut reader)?,
+            read_len(&mut reader)?,
+        );
+        remaining -= SHAPE_BYTES;
+        let value_bytes = shape
+            .0
+            .checked_mul(shape.1)
+            .and_then(|len| len.checked_mul(shape.2))
+            .and_then(|len| len.checked_mul(VALUE_BYTES))
+            .filter(|value_bytes| *value_bytes <= remaining)
+            .ok_or(WeightsError::InvalidFileError)?;
+        remaining -= value_bytes;
+
+        let mut bytes = vec![0; value_bytes];
+        reader.read_exact(&mut bytes)?;
+        let values = bytes
+            .chunks_exact(VALUE_BYTES)
+            .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+            .collect();
+        weights.push(Array::from_shape_vec(shape, values)?);
+    }
+    Ok(weights)
+}
+
+fn read_len(reader: &mut impl Read) -> Result<usize, Box<dyn Error>> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(usize::try_from(u64::from_le_bytes(bytes))?)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WeightsError {
+    #[error("not a carnaval_rust weights file")]
+    InvalidFileError,
+    #[error("weights do not match the model parameters")]
+    ShapeMismatchError,
+}
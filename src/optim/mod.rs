@@ -0,0 +1,272 @@
+// optimizers
+//
+// An Optimizer walks the parameters of a model in the order given by
+// SequentialModel::parameters_mut (or FunctionalModel::parameters_mut, given
+// to step_parameters) and updates them from their accumulated gradients.
+// Per-parameter state (moments, squared gradient sums) is indexed by that
+// order, so one optimizer should only be used with one model.
+
+pub mod scheduler;
+
+use std::error::Error;
+
+use ndarray::{Array, Ix3, Zip};
+use scheduler::LearningRateScheduler;
+
+use crate::{layer::Parameter, model::sequential::SequentialModel};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum OptimizerType {
+    Sgd,
+    Momentum {
+        momentum: f32,
+    },
+    Adam {
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+    },
+    AdamW {
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        weight_decay: f32,
+    },
+    RmsProp {
+        rho: f32,
+        epsilon: f32,
+    },
+    Adagrad {
+        epsilon: f32,
+    },
+}
+
+pub struct Optimizer {
+    pub optimizer_type: OptimizerType,
+    pub learning_rate: f32,
+    base_learning_rate: f32,
+    scheduler: Option<LearningRateScheduler>,
+    iterations: i32,
+    first_moments: Vec<Array<f32, Ix3>>,
+    second_moments: Vec<Array<f32, Ix3>>,
+}
+
+impl Optimizer {
+    pub fn new(optimizer_type: OptimizerType, learning_rate: f32) -> Self {
+        Self {
+            optimizer_type,
+            learning_rate,
+            base_learning_rate: learning_rate,
+            scheduler: None,
+            iterations: 0,
+            first_moments: Vec::new(),
+            second_moments: Vec::new(),
+        }
+    }
+
+    pub fn sgd(learning_rate: f32) -> Self {
+        Self::new(OptimizerType::Sgd, learning_rate)
+    }
+
+    pub fn momentum(learning_rate: f32) -> Self {
+        Self::new(OptimizerType::Momentum { momentum: 0.9 }, learning_rate)
+    }
+
+    pub fn adam(learning_rate: f32) -> Self {
+        Self::new(
+            OptimizerType::Adam {
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-7,
+            },
+            learning_rate,
+        )
+    }
+
+    pub fn adam_w(learning_rate: f32) -> Self {
+        Self::new(
+            OptimizerType::AdamW {
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-7,
+                weight_decay: 0.01,
+            },
+            learning_rate,
+        )
+    }
+
+    pub fn rms_prop(learning_rate: f32) -> Self {
+        Self::new(
+            OptimizerType::RmsProp {
+                rho: 0.9,
+                epsilon: 1e-7,
+            },
+            learning_rate,
+        )
+    }
+
+    pub fn adagrad(learning_rate: f32) -> Self {
+        Self::new(OptimizerType::Adagrad { epsilon: 1e-7 }, learning_rate)
+    }
+}
+
+impl Optimizer {
+    // The learning rate given at creation becomes the base the scheduler
+    // scales from
+    #[must_use]
+    pub fn with_scheduler(mut self, scheduler: LearningRateScheduler) -> Self {
+        self.learning_rate = scheduler.learning_rate(self.base_learning_rate, self.learning_rate);
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    pub fn scheduler(&self) -> Option<&LearningRateScheduler> {
+        self.scheduler.as_ref()
+    }
+
+    // Applies one update to every parameter of the model. Gradients are left
+    // untouched, call SequentialModel::zero_gradients before the next batch.
+    pub fn step(&mut self, model: &mut SequentialModel) -> Result<(), Box<dyn Error>> {
+        self.step_parameters(model.parameters_mut())
+    }
+
+    // Same as step, for parameters given in the same order at every call
+    pub fn step_parameters(
+        &mut self,
+        parameters: Vec<Parameter<'_>>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.iterations += 1;
+        for (index, parameter) in parameters.into_iter().enumerate() {
+            self.update(index, parameter)?;
+        }
+
+        if let Some(scheduler) = &mut self.scheduler {
+            scheduler.end_batch();
+            self.learning_rate =
+                scheduler.learning_rate(self.base_learning_rate, self.learning_rate);
+        }
+        Ok(())
+    }
+
+    // Lets the scheduler move to the next epoch. monitored_loss is the
+    // validation loss when available, used by ReduceOnPlateau.
+    pub fn end_epoch(&mut self, monitored_loss: Option<f32>) {
+        if let Some(scheduler) = &mut self.scheduler {
+            self.learning_rate =
+                scheduler.end_epoch(self.base_learning_rate, self.learning_rate, monitored_loss);
+        }
+    }
+
+    // Forgets every moment, e.g. before training another model. The scheduler
+    // keeps its progress.
+    pub fn reset(&mut self) {
+        self.iterations = 0;
+        self.first_moments.clear();
+        self.second_moments.clear();
+    }
+
+    fn update(&mut self, index: usize, parameter: Parameter<'_>) -> Result<(), Box<dyn Error>> {
+        let Parameter { value, gradient } = parameter;
+        if value.shape() != gradient.shape() {
+            return Err(Box::new(OptimizerError::ParameterShapeError));
+        }
+        let learning_rate = self.learning_rate;
+
+        match self.optimizer_type {
+            OptimizerType::Sgd => value.scaled_add(-learning_rate, gradient),
+            OptimizerType::Momentum { momentum } => {
+                let velocity = moment(&mut self.first_moments, index, value)?;
+                Zip::from(&mut *velocity)
+                    .and(gradient)
+                    .for_each(|v, g| *v = momentum * *v + g);
+                value.scaled_add(-learning_rate, velocity);
+            }
+            OptimizerType::Adam {
+                beta1,
+                beta2,
+                epsilon,
+            } => self.adam_update(index, value, gradient, beta1, beta2, epsilon)?,
+            OptimizerType::AdamW {
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            } => {
+                // decoupled weight decay, applied outside of the moments
+                *value *= 1.0 - learning_rate * weight_decay;
+                self.adam_update(index, value, gradient, beta1, beta2, epsilon)?;
+            }
+            OptimizerType::RmsProp { rho, epsilon } => {
+                let mean_square = moment(&mut self.second_moments, index, value)?;
+                Zip::from(value)
+                    .and(mean_square)
+                    .and(gradient)
+                    .for_each(|w, v, g| {
+                        *v = rho * *v + (1.0 - rho) * g * g;
+                        *w -= learning_rate * g / (v.sqrt() + epsilon);
+                    });
+            }
+            OptimizerType::Adagrad { epsilon } => {
+                let square_sum = moment(&mut self.second_moments, index, value)?;
+                Zip::from(value)
+                    .and(square_sum)
+                    .and(gradient)
+                    .for_each(|w, v, g| {
+                        *v += g * g;
+                        *w -= learning_rate * g / (v.sqrt() + epsilon);
+                    });
+            }
+        }
+        Ok(())
+    }
+
+    fn adam_update(
+        &mut self,
+        index: usize,
+        value: &mut Array<f32, Ix3>,
+        gradient: &Array<f32, Ix3>,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        let learning_rate = self.learning_rate;
+        let first_correction = 1.0 - beta1.powi(self.iterations);
+        let second_correction = 1.0 - beta2.powi(self.iterations);
+        let first_moment = moment(&mut self.first_moments, index, value)?;
+        let second_moment = moment(&mut self.second_moments, index, value)?;
+
+        Zip::from(value)
+            .and(first_moment)
+            .and(second_moment)
+            .and(gradient)
+            .for_each(|w, m, v, g| {
+                *m = beta1 * *m + (1.0 - beta1) * g;
+                *v = beta2 * *v + (1.0 - beta2) * g * g;
+                let m_hat = *m / first_correction;
+                let v_hat = *v / second_correction;
+                *w -= learning_rate * m_hat / (v_hat.sqrt() + epsilon);
+            });
+        Ok(())
+    }
+}
+
+// State of the parameter at index, created with zeros on its first update
+fn moment<'a>(
+    moments: &'a mut Vec<Array<f32, Ix3>>,
+    index: usize,
+    value: &Array<f32, Ix3>,
+) -> Result<&'a mut Array<f32, Ix3>, Box<dyn Error>> {
+    if index == moments.len() {
+        moments.push(Array::zeros(value.raw_dim()));
+    }
+    match moments.get_mut(index) {
+        Some(moment) if moment.shape() == value.shape() => Ok(moment),
+        _ => Err(Box::new(OptimizerError::ParameterShapeError)),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OptimizerError {
+    #[error("parameters changed since the optimizer state was created")]
+    ParameterShapeError,
+}
@@ -0,0 +1,197 @@
+// learning-rate schedulers
+//
+// A scheduler is attached to an Optimizer with Optimizer::with_scheduler and
+// scales the learning rate the optimizer was created with (the base learning
+// rate). Step based schedules are advanced by Optimizer::step, epoch based
+// ones by Optimizer::end_epoch, which SequentialModel::fit calls.
+
+use std::f32::consts::PI;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SchedulerType {
+    // base * gamma ^ (epoch / step_size)
+    StepDecay {
+        step_size: usize,
+        gamma: f32,
+    },
+    // base * decay_rate ^ epoch
+    ExponentialDecay {
+        decay_rate: f32,
+    },
+    // Linear warmup from 0 to base, then cosine annealing down to
+    // min_learning_rate at total_steps
+    CosineAnnealing {
+        warmup_steps: usize,
+        total_steps: usize,
+        min_learning_rate: f32,
+    },
+    // Cosine increase from base / div_factor to base during the first
+    // pct_start of total_steps, then cosine decrease down to
+    // base / (div_factor * final_div_factor)
+    OneCycle {
+        total_steps: usize,
+        pct_start: f32,
+        div_factor: f32,
+        final_div_factor: f32,
+    },
+    // Multiplies the learning rate by factor when the monitored loss does not
+    // improve for patience epochs
+    ReduceOnPlateau {
+        factor: f32,
+        patience: usize,
+        min_learning_rate: f32,
+    },
+}
+
+pub struct LearningRateScheduler {
+    pub scheduler_type: SchedulerType,
+    steps: usize,
+    epochs: usize,
+    best_loss: f32,
+    epochs_without_improvement: usize,
+}
+
+impl LearningRateScheduler {
+    pub fn new(scheduler_type: SchedulerType) -> Self {
+        Self {
+            scheduler_type,
+            steps: 0,
+            epochs: 0,
+            best_loss: f32::INFINITY,
+            epochs_without_improvement: 0,
+        }
+    }
+
+    pub fn step_decay(step_size: usize, gamma: f32) -> Self {
+        Self::new(SchedulerType::StepDecay { step_size, gamma })
+    }
+
+    pub fn exponential_decay(decay_rate: f32) -> Self {
+        Self::new(SchedulerType::ExponentialDecay { decay_rate })
+    }
+
+    pub fn cosine_annealing(warmup_steps: usize, total_steps: usize) -> Self {
+        Self::new(SchedulerType::CosineAnnealing {
+            warmup_steps,
+            total_steps,
+            min_learning_rate: 0.0,
+        })
+    }
+
+    pub fn one_cycle(total_steps: usize) -> Self {
+        Self::new(SchedulerType::OneCycle {
+            total_steps,
+            pct_start: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1e4,
+        })
+    }
+
+    pub fn reduce_on_plateau(factor: f32, patience: usize) -> Self {
+        Self::new(SchedulerType::ReduceOnPlateau {
+            factor,
+            patience,
+            min_learning_rate: 0.0,
+        })
+    }
+}
+
+impl LearningRateScheduler {
+    // Learning rate for the next batch, given the current one
+    #[expect(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_wrap,
+        clippy::cast_possible_truncation
+    )]
+    pub fn learning_rate(&self, base_learning_rate: f32, current_learning_rate: f32) -> f32 {
+        match self.scheduler_type {
+            SchedulerType::StepDecay { step_size, gamma } => {
+                base_learning_rate * gamma.powi((self.epochs / step_size.max(1)) as i32)
+            }
+            SchedulerType::ExponentialDecay { decay_rate } => {
+                base_learning_rate * decay_rate.powi(self.epochs as i32)
+            }
+            SchedulerType::CosineAnnealing {
+                warmup_steps,
+                total_steps,
+                min_learning_rate,
+            } => {
+                if self.steps < warmup_steps {
+                    base_learning_rate * (self.steps + 1) as f32 / warmup_steps as f32
+                } else {
+                    let progress = (self.steps - warmup_steps) as f32
+                        / total_steps.saturating_sub(warmup_steps).max(1) as f32;
+                    cosine_interpolation(base_learning_rate, min_learning_rate, progress)
+                }
+            }
+            SchedulerType::OneCycle {
+                total_steps,
+                pct_start,
+                div_factor,
+                final_div_factor,
+            } => {
+                let initial_learning_rate = base_learning_rate / div_factor;
+                let final_learning_rate = initial_learning_rate / final_div_factor;
+                let warmup_steps = (pct_start * total_steps as f32).max(1.0);
+                let steps = self.steps as f32;
+                if steps < warmup_steps {
+                    cosine_interpolation(
+                        initial_learning_rate,
+                        base_learning_rate,
+                        steps / warmup_steps,
+                    )
+                } else {
+                    let progress =
+                        (steps - warmup_steps) / (total_steps as f32 - warmup_steps).max(1.0);
+                    cosine_interpolation(base_learning_rate, final_learning_rate, progress)
+                }
+            }
+            SchedulerType::ReduceOnPlateau { .. } => current_learning_rate,
+        }
+    }
+
+    pub(crate) fn end_batch(&mut self) {
+        self.steps += 1;
+    }
+
+    // Returns the learning rate for the next epoch. monitored_loss is only
+    // used by ReduceOnPlateau.
+    pub(crate) fn end_epoch(
+        &mut self,
+        base_learning_rate: f32,
+        current_learning_rate: f32,
+        monitored_loss: Option<f32>,
+    ) -> f32 {
+        self.epochs += 1;
+        let SchedulerType::ReduceOnPlateau {
+            factor,
+            patience,
+            min_learning_rate,
+        } = self.scheduler_type
+        else {
+            return self.learning_rate(base_learning_rate, current_learning_rate);
+        };
+        let Some(loss) = monitored_loss else {
+            return current_learning_rate;
+        };
+
+        if loss < self.best_loss {
+            self.best_loss = loss;
+            self.epochs_without_improvement = 0;
+            return current_learning_rate;
+        }
+        self.epochs_without_improvement += 1;
+        if self.epochs_without_improvement >= patience {
+            self.epochs_without_improvement = 0;
+            (current_learning_rate * factor).max(min_learning_rate)
+        } else {
+            current_learning_rate
+        }
+    }
+}
+
+// Goes from start (progress 0) to end (progress 1) following half a cosine
+fn cosine_interpolation(start: f32, end: f32, progress: f32) -> f32 {
+    let progress = progress.clamp(0.0, 1.0);
+    end + 0.5 * (start - end) * (1.0 + (PI * progress).cos())
+}